@@ -0,0 +1,55 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use critwm::{bench, settings::Settings};
+use std::mem;
+use x11_dl::xlib;
+
+// Events that handle_event resolves to a client before handling them, each referring to a
+// different window.
+fn client_events(windows: &[xlib::Window]) -> Vec<xlib::XEvent> {
+    windows
+        .iter()
+        .enumerate()
+        .map(|(i, window)| {
+            let mut event: xlib::XEvent = unsafe { mem::zeroed() };
+            match i % 3 {
+                0 => {
+                    event.crossing.type_ = xlib::EnterNotify;
+                    event.crossing.window = *window;
+                }
+                1 => {
+                    event.property.type_ = xlib::PropertyNotify;
+                    event.property.window = *window;
+                }
+                _ => {
+                    event.configure_request.type_ = xlib::ConfigureRequest;
+                    event.configure_request.window = *window;
+                }
+            }
+            event
+        })
+        .collect()
+}
+
+// Every event about a window is matched to its client before it is handled, so dispatching
+// should only get slower as more windows are managed by arranging and restacking them.
+fn dispatch_events(c: &mut Criterion) {
+    let settings = Settings::default();
+    let mut group = c.benchmark_group("dispatch_events");
+    for count in [10, 100, 1000] {
+        let mut clients = bench::clients(count);
+        // The most recently added windows are the last ones found by scanning the clients.
+        let windows: Vec<xlib::Window> = (count - 8..=count).collect();
+        let events = client_events(&windows);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &events, |b, events| {
+            b.iter(|| {
+                for event in events {
+                    bench::dispatch(&mut clients, black_box(event), &settings);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, dispatch_events);
+criterion_main!(benches);
@@ -1,5 +1,9 @@
 use crate::{
-    backend::{client::Client, monitor::Monitor, Backend},
+    backend::{
+        client::{ClientId, Clients},
+        monitor::Monitor,
+        Backend,
+    },
     config,
     layouts::Layout,
 };
@@ -8,11 +12,11 @@ use serde::Serialize;
 // Serialized backend.
 #[derive(Serialize)]
 pub struct Api<'a> {
-    clients: &'a Vec<Client>,
+    clients: &'a Clients,
     layouts: &'a Vec<Layout>,
     monitors: &'a Vec<Monitor<{ config::WORKSPACE_COUNT }>>,
-    workspaces: &'a [&'a str; config::WORKSPACE_COUNT],
-    current_client: &'a Option<usize>,
+    workspaces: &'a Vec<String>,
+    current_client: &'a Option<ClientId>,
     current_monitor: usize,
 }
 
@@ -20,9 +24,9 @@ impl<'a> From<&'a Backend<'a>> for Api<'a> {
     fn from(backend: &'a Backend<'a>) -> Self {
         Self {
             clients: &backend.clients,
-            layouts: &backend.layouts,
+            layouts: &backend.settings.layouts,
             monitors: &backend.monitors,
-            workspaces: &config::WORKSPACES,
+            workspaces: &backend.settings.workspaces,
             current_client: &backend.current_client,
             current_monitor: backend.current_monitor,
         }
@@ -1,3 +1,4 @@
+use crate::backend::client::WindowState;
 use std::ffi::CString;
 use x11_dl::xlib;
 
@@ -5,6 +6,8 @@ use x11_dl::xlib;
 pub struct Atom {
     pub wm_protocols: xlib::Atom,
     pub wm_delete: xlib::Atom,
+    pub wm_take_focus: xlib::Atom,
+    pub wm_state: xlib::Atom,
     pub net_wm_name: xlib::Atom,
     pub net_supporting_wm_check: xlib::Atom,
     pub net_supported: xlib::Atom,
@@ -13,6 +16,30 @@ pub struct Atom {
     pub net_wm_window_type_dock: xlib::Atom,
     pub net_wm_state: xlib::Atom,
     pub net_wm_state_fullscreen: xlib::Atom,
+    pub net_wm_state_above: xlib::Atom,
+    pub net_wm_state_sticky: xlib::Atom,
+    pub net_wm_state_hidden: xlib::Atom,
+    pub net_wm_state_maximized_vert: xlib::Atom,
+    pub net_wm_state_maximized_horz: xlib::Atom,
+    pub net_wm_state_demands_attention: xlib::Atom,
+    pub net_wm_state_skip_taskbar: xlib::Atom,
+    pub wm_window_role: xlib::Atom,
+    pub net_wm_pid: xlib::Atom,
+    pub net_number_of_desktops: xlib::Atom,
+    pub net_current_desktop: xlib::Atom,
+    pub net_desktop_names: xlib::Atom,
+    pub net_desktop_viewport: xlib::Atom,
+    pub net_wm_desktop: xlib::Atom,
+    pub net_client_list: xlib::Atom,
+    pub net_client_list_stacking: xlib::Atom,
+    pub net_active_window: xlib::Atom,
+    pub net_close_window: xlib::Atom,
+    pub net_moveresize_window: xlib::Atom,
+    pub net_wm_strut: xlib::Atom,
+    pub net_wm_strut_partial: xlib::Atom,
+    pub net_workarea: xlib::Atom,
+    pub utf8_string: xlib::Atom,
+    pub critwm_session: xlib::Atom,
 }
 
 impl Atom {
@@ -20,6 +47,8 @@ impl Atom {
         Self {
             wm_protocols: Self::get_atom(xlib, display, "WM_PROTOCOLS"),
             wm_delete: Self::get_atom(xlib, display, "WM_DELETE_WINDOW"),
+            wm_take_focus: Self::get_atom(xlib, display, "WM_TAKE_FOCUS"),
+            wm_state: Self::get_atom(xlib, display, "WM_STATE"),
             net_wm_name: Self::get_atom(xlib, display, "_NET_WM_NAME"),
             net_supporting_wm_check: Self::get_atom(xlib, display, "_NET_SUPPORTING_WM_CHECK"),
             net_supported: Self::get_atom(xlib, display, "_NET_SUPPORTED"),
@@ -28,6 +57,42 @@ impl Atom {
             net_wm_window_type_dock: Self::get_atom(xlib, display, "_NET_WM_WINDOW_TYPE_DOCK"),
             net_wm_state: Self::get_atom(xlib, display, "_NET_WM_STATE"),
             net_wm_state_fullscreen: Self::get_atom(xlib, display, "_NET_WM_STATE_FULLSCREEN"),
+            net_wm_state_above: Self::get_atom(xlib, display, "_NET_WM_STATE_ABOVE"),
+            net_wm_state_sticky: Self::get_atom(xlib, display, "_NET_WM_STATE_STICKY"),
+            net_wm_state_hidden: Self::get_atom(xlib, display, "_NET_WM_STATE_HIDDEN"),
+            net_wm_state_maximized_vert: Self::get_atom(
+                xlib,
+                display,
+                "_NET_WM_STATE_MAXIMIZED_VERT",
+            ),
+            net_wm_state_maximized_horz: Self::get_atom(
+                xlib,
+                display,
+                "_NET_WM_STATE_MAXIMIZED_HORZ",
+            ),
+            net_wm_state_demands_attention: Self::get_atom(
+                xlib,
+                display,
+                "_NET_WM_STATE_DEMANDS_ATTENTION",
+            ),
+            net_wm_state_skip_taskbar: Self::get_atom(xlib, display, "_NET_WM_STATE_SKIP_TASKBAR"),
+            wm_window_role: Self::get_atom(xlib, display, "WM_WINDOW_ROLE"),
+            net_wm_pid: Self::get_atom(xlib, display, "_NET_WM_PID"),
+            net_number_of_desktops: Self::get_atom(xlib, display, "_NET_NUMBER_OF_DESKTOPS"),
+            net_current_desktop: Self::get_atom(xlib, display, "_NET_CURRENT_DESKTOP"),
+            net_desktop_names: Self::get_atom(xlib, display, "_NET_DESKTOP_NAMES"),
+            net_desktop_viewport: Self::get_atom(xlib, display, "_NET_DESKTOP_VIEWPORT"),
+            net_wm_desktop: Self::get_atom(xlib, display, "_NET_WM_DESKTOP"),
+            net_client_list: Self::get_atom(xlib, display, "_NET_CLIENT_LIST"),
+            net_client_list_stacking: Self::get_atom(xlib, display, "_NET_CLIENT_LIST_STACKING"),
+            net_active_window: Self::get_atom(xlib, display, "_NET_ACTIVE_WINDOW"),
+            net_close_window: Self::get_atom(xlib, display, "_NET_CLOSE_WINDOW"),
+            net_moveresize_window: Self::get_atom(xlib, display, "_NET_MOVERESIZE_WINDOW"),
+            net_wm_strut: Self::get_atom(xlib, display, "_NET_WM_STRUT"),
+            net_wm_strut_partial: Self::get_atom(xlib, display, "_NET_WM_STRUT_PARTIAL"),
+            net_workarea: Self::get_atom(xlib, display, "_NET_WORKAREA"),
+            utf8_string: Self::get_atom(xlib, display, "UTF8_STRING"),
+            critwm_session: Self::get_atom(xlib, display, "_CRITWM_SESSION"),
         }
     }
 
@@ -41,9 +106,48 @@ impl Atom {
             self.net_wm_window_type_dock,
             self.net_wm_state,
             self.net_wm_state_fullscreen,
+            self.net_wm_state_above,
+            self.net_wm_state_sticky,
+            self.net_wm_state_hidden,
+            self.net_wm_state_maximized_vert,
+            self.net_wm_state_maximized_horz,
+            self.net_wm_state_demands_attention,
+            self.net_wm_state_skip_taskbar,
+            self.net_number_of_desktops,
+            self.net_current_desktop,
+            self.net_desktop_names,
+            self.net_desktop_viewport,
+            self.net_wm_desktop,
+            self.net_client_list,
+            self.net_client_list_stacking,
+            self.net_active_window,
+            self.net_close_window,
+            self.net_moveresize_window,
+            self.net_wm_strut,
+            self.net_wm_strut_partial,
+            self.net_workarea,
         ]
     }
 
+    pub fn state_atom(&self, state: WindowState) -> xlib::Atom {
+        match state {
+            WindowState::Fullscreen => self.net_wm_state_fullscreen,
+            WindowState::Above => self.net_wm_state_above,
+            WindowState::Sticky => self.net_wm_state_sticky,
+            WindowState::Hidden => self.net_wm_state_hidden,
+            WindowState::MaximizedVert => self.net_wm_state_maximized_vert,
+            WindowState::MaximizedHorz => self.net_wm_state_maximized_horz,
+            WindowState::DemandsAttention => self.net_wm_state_demands_attention,
+            WindowState::SkipTaskbar => self.net_wm_state_skip_taskbar,
+        }
+    }
+
+    pub fn window_state(&self, atom: xlib::Atom) -> Option<WindowState> {
+        WindowState::ALL
+            .into_iter()
+            .find(|state| self.state_atom(*state) == atom)
+    }
+
     fn get_atom(xlib: &xlib::Xlib, display: *mut xlib::Display, name: &str) -> xlib::Atom {
         unsafe {
             (xlib.XInternAtom)(
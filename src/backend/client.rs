@@ -1,8 +1,16 @@
-use crate::util::{XWindowDimension, XWindowPosition};
-use serde::Serialize;
+use crate::{
+    backend::monitor::Strut,
+    rules::WindowProperties,
+    util::{XWindowDimension, XWindowPosition},
+};
+use serde::{ser::SerializeSeq, Deserialize, Serialize, Serializer};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::{Index, IndexMut},
+};
 use x11_dl::xlib;
 
-#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct WindowGeometry {
     pub x: i32,
     pub y: i32,
@@ -70,18 +78,82 @@ impl WindowGeometry {
     }
 }
 
+// States of a window from _NET_WM_STATE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum WindowState {
+    Fullscreen,
+    Above,
+    Sticky,
+    Hidden,
+    MaximizedVert,
+    MaximizedHorz,
+    DemandsAttention,
+    SkipTaskbar,
+}
+
+impl WindowState {
+    pub const ALL: [Self; 8] = [
+        Self::Fullscreen,
+        Self::Above,
+        Self::Sticky,
+        Self::Hidden,
+        Self::MaximizedVert,
+        Self::MaximizedHorz,
+        Self::DemandsAttention,
+        Self::SkipTaskbar,
+    ];
+}
+
+// Identifier of a managed client, which stays the same for as long as the client is managed.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct ClientId(u64);
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Client {
+    // Assigned when the client is added to Clients.
+    pub id: ClientId,
     geometry: WindowGeometry,
     // old_geometry stores the geometry of the window before fullscreen was toggled.
     old_geometry: WindowGeometry,
     pub window: xlib::Window,
     pub monitor: usize,
+    // Name of the monitor the client was on before it was disconnected. The client is moved back
+    // once the monitor is connected again.
+    #[serde(skip_serializing)]
+    pub origin_monitor: Option<String>,
     pub workspace: usize,
     pub fullscreen: bool,
     pub floating: bool,
+    // Every state of the window, which is written back to its _NET_WM_STATE.
+    pub states: BTreeSet<WindowState>,
+    // Set from the urgency flag of WM_HINTS until the client is focused.
+    pub urgent: bool,
+    // Clients that set the input field of WM_HINTS to false never get input focus from critwm.
+    #[serde(skip_serializing)]
+    pub never_focus: bool,
+    // Whether the window is mapped, and how many of the unmaps requested by critwm have not been
+    // notified yet. Other unmaps are made by the client to withdraw the window.
+    #[serde(skip_serializing)]
+    mapped: bool,
+    #[serde(skip_serializing)]
+    expected_unmaps: usize,
+    // Border width the window had before it was managed, which is restored once it is withdrawn.
+    #[serde(skip_serializing)]
+    pub original_border_width: i32,
     // Indicates if window's type is _NET_WM_WINDOW_TYPE_DOCK.
     pub dock: bool,
+    // Window this window is a transient for, such as the parent of a dialog.
+    pub transient_for: Option<xlib::Window>,
+    // Space reserved by a dock.
+    #[serde(skip_serializing)]
+    pub strut: Option<Strut>,
+    // Title, class, instance and role of the window.
+    #[serde(flatten)]
+    pub properties: WindowProperties,
+    // Process ID from _NET_WM_PID.
+    pub pid: Option<u32>,
 }
 
 impl Client {
@@ -93,36 +165,61 @@ impl Client {
         workspace: usize,
     ) -> Self {
         let geometry = WindowGeometry::fetch(xlib, display, &window);
+        let original_border_width = geometry.border_width;
         Self {
+            id: ClientId::default(),
             geometry: geometry.clone(),
             old_geometry: geometry,
             window,
             monitor,
+            origin_monitor: None,
             workspace,
             fullscreen: false,
             floating: false,
+            states: BTreeSet::new(),
+            urgent: false,
+            never_focus: false,
+            mapped: false,
+            expected_unmaps: 0,
+            original_border_width,
             dock: false,
+            strut: None,
+            transient_for: None,
+            properties: WindowProperties::default(),
+            pid: None,
         }
     }
 
-    #[cfg(test)]
-    pub fn new(geometry: WindowGeometry, monitor: usize, workspace: usize) -> Self {
+    pub(crate) fn new(geometry: WindowGeometry, monitor: usize, workspace: usize) -> Self {
         let old_geometry = geometry.clone();
         Self {
+            id: ClientId::default(),
             geometry,
             old_geometry,
             window: 0,
             monitor,
+            origin_monitor: None,
             workspace,
             fullscreen: false,
             floating: false,
+            states: BTreeSet::new(),
+            urgent: false,
+            never_focus: false,
+            mapped: false,
+            expected_unmaps: 0,
+            original_border_width: 0,
             dock: false,
+            strut: None,
+            transient_for: None,
+            properties: WindowProperties::default(),
+            pid: None,
         }
     }
 
     #[cfg(test)]
     pub fn fullscreen(mut self) -> Self {
         self.fullscreen = true;
+        self.states.insert(WindowState::Fullscreen);
         self
     }
 
@@ -144,6 +241,10 @@ impl Client {
         &self.old_geometry
     }
 
+    pub fn set_old_geometry(&mut self, geometry: WindowGeometry) {
+        self.old_geometry = geometry;
+    }
+
     pub fn update_geometry(&mut self, xlib: &xlib::Xlib, display: *mut xlib::Display) {
         self.geometry = WindowGeometry::fetch(xlib, display, &self.window);
     }
@@ -154,5 +255,388 @@ impl Client {
             self.old_geometry = self.geometry.clone();
         }
         self.fullscreen = !self.fullscreen;
+        self.set_state(WindowState::Fullscreen, self.fullscreen);
+    }
+
+    // Record that critwm maps or unmaps the window. Only unmapping a mapped window generates an
+    // UnmapNotify, which is then expected.
+    pub fn set_mapped(&mut self, mapped: bool) {
+        if self.mapped && !mapped {
+            self.expected_unmaps += 1;
+        }
+        self.mapped = mapped;
+    }
+
+    // Returns true if an UnmapNotify was caused by critwm, and false if the client withdrew the
+    // window.
+    pub fn take_expected_unmap(&mut self) -> bool {
+        if self.expected_unmaps == 0 {
+            return false;
+        }
+        self.expected_unmaps -= 1;
+        true
+    }
+
+    // Whether the client can be focused on the given workspace of a monitor.
+    pub fn is_focusable_on(&self, monitor: usize, workspace: usize) -> bool {
+        self.monitor == monitor
+            && self.workspace == workspace
+            && !self.dock
+            && !self.has_state(WindowState::Hidden)
+    }
+
+    pub fn has_state(&self, state: WindowState) -> bool {
+        self.states.contains(&state)
+    }
+
+    // Fullscreen must be changed with toggle_fullscreen, which also stores the geometry.
+    pub fn set_state(&mut self, state: WindowState, enabled: bool) {
+        if enabled {
+            self.states.insert(state);
+        } else {
+            self.states.remove(&state);
+        }
+    }
+}
+
+// Managed clients, kept in the order they were added. Ids only increase, so ordering clients by
+// id keeps them in that order.
+#[derive(Debug, Default)]
+pub struct Clients {
+    clients: BTreeMap<ClientId, Client>,
+    // Stacking order from the bottom to the top, kept as windows are raised so that it does not
+    // have to be queried from the server. Removing a client from it is linear in the number of
+    // clients, but only done when a window is withdrawn or destroyed.
+    stack: Vec<ClientId>,
+    // Client of every managed window, as events only refer to windows.
+    windows: HashMap<xlib::Window, ClientId>,
+    next_id: u64,
+}
+
+impl Clients {
+    pub fn insert(&mut self, mut client: Client) -> ClientId {
+        self.next_id += 1;
+        let id = ClientId(self.next_id);
+        client.id = id;
+        self.windows.insert(client.window, id);
+        self.clients.insert(id, client);
+        // Mapped windows are placed at the top of the stack.
+        self.stack.push(id);
+        id
+    }
+
+    pub fn remove(&mut self, id: ClientId) -> Option<Client> {
+        let client = self.clients.remove(&id)?;
+        self.stack.retain(|client| *client != id);
+        if self.windows.get(&client.window) == Some(&id) {
+            self.windows.remove(&client.window);
+        }
+        Some(client)
+    }
+
+    pub fn get(&self, id: ClientId) -> Option<&Client> {
+        self.clients.get(&id)
+    }
+
+    pub fn contains(&self, id: ClientId) -> bool {
+        self.clients.contains_key(&id)
+    }
+
+    // Find the client managing a window.
+    pub fn find(&self, window: xlib::Window) -> Option<ClientId> {
+        self.windows.get(&window).copied()
+    }
+
+    // Find the client an event is about, for the events that refer to a client window.
+    pub fn event_client(&self, event: &xlib::XEvent) -> Option<ClientId> {
+        let window = unsafe {
+            match event.get_type() {
+                xlib::ConfigureRequest => event.configure_request.window,
+                xlib::MapRequest => event.map_request.window,
+                xlib::UnmapNotify => event.unmap.window,
+                xlib::DestroyNotify => event.destroy_window.window,
+                xlib::EnterNotify => event.crossing.window,
+                xlib::PropertyNotify => event.property.window,
+                _ => return None,
+            }
+        };
+        self.find(window)
+    }
+
+    // The most recently added client that can be focused on the given workspace of a monitor.
+    pub fn last_on(&self, monitor: usize, workspace: usize) -> Option<ClientId> {
+        self.clients
+            .values()
+            .rev()
+            .find(|client| client.is_focusable_on(monitor, workspace))
+            .map(|client| client.id)
+    }
+
+    // Ids of every client, which can be iterated while clients are changed.
+    pub fn ids(&self) -> Vec<ClientId> {
+        self.clients.keys().copied().collect()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Client> + Clone {
+        self.clients.values()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Client> {
+        self.clients.values_mut()
+    }
+
+    // Clients from the bottom of the stack to the top.
+    pub fn stacking(&self) -> impl DoubleEndedIterator<Item = &Client> {
+        self.stack.iter().map(|id| &self.clients[id])
+    }
+
+    pub fn raise(&mut self, id: ClientId) {
+        self.stack.retain(|client| *client != id);
+        self.stack.push(id);
+    }
+
+    // Move a client directly above another client.
+    pub fn place_above(&mut self, id: ClientId, sibling: ClientId) {
+        self.stack.retain(|client| *client != id);
+        let position = self
+            .stack
+            .iter()
+            .position(|client| *client == sibling)
+            .map_or(self.stack.len(), |position| position + 1);
+        self.stack.insert(position, id);
+    }
+
+    // Shown clients that should be raised because they stay above others, and shown transients
+    // paired with the shown parent they should be placed above, in the order to apply them.
+    pub fn restacking(
+        &self,
+        is_shown: impl Fn(&Client) -> bool,
+    ) -> (Vec<ClientId>, Vec<(ClientId, ClientId)>) {
+        let above = self
+            .iter()
+            .filter(|client| client.has_state(WindowState::Above) && is_shown(client))
+            .map(|client| client.id)
+            .collect();
+        let transients = self
+            .iter()
+            .filter(|client| is_shown(client))
+            .filter_map(|client| {
+                client
+                    .transient_for
+                    .and_then(|window| self.find(window))
+                    .filter(|parent| is_shown(&self.clients[parent]))
+                    .map(|parent| (client.id, parent))
+            })
+            .collect();
+        (above, transients)
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+}
+
+impl Index<ClientId> for Clients {
+    type Output = Client;
+
+    fn index(&self, id: ClientId) -> &Client {
+        &self.clients[&id]
+    }
+}
+
+impl IndexMut<ClientId> for Clients {
+    fn index_mut(&mut self, id: ClientId) -> &mut Client {
+        self.clients.get_mut(&id).expect("client is not managed")
+    }
+}
+
+impl Serialize for Clients {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for client in self.iter() {
+            seq.serialize_element(client)?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Client, Clients, WindowGeometry, WindowState};
+    use crate::rules::WindowProperties;
+    use std::mem;
+    use x11_dl::xlib;
+
+    #[test]
+    fn serialize_properties() {
+        let mut client = Client::new(WindowGeometry::new(0, 0, 100, 100, 1), 0, 0);
+        client.properties = WindowProperties {
+            class: Some("Firefox".to_owned()),
+            instance: Some("Navigator".to_owned()),
+            title: Some("Mozilla Firefox".to_owned()),
+            role: None,
+        };
+        client.pid = Some(1234);
+        client.urgent = true;
+        client.never_focus = true;
+        let value = serde_json::to_value(&client).unwrap();
+        assert_eq!(value["title"], "Mozilla Firefox");
+        assert_eq!(value["class"], "Firefox");
+        assert_eq!(value["instance"], "Navigator");
+        assert!(value["role"].is_null());
+        assert_eq!(value["pid"], 1234);
+        assert_eq!(value["urgent"], true);
+        assert!(value.get("never_focus").is_none());
+    }
+
+    #[test]
+    fn fullscreen_state() {
+        let mut client = Client::new(WindowGeometry::new(0, 0, 100, 100, 1), 0, 0);
+        client.set_state(WindowState::Above, true);
+        client.toggle_fullscreen();
+        assert!(client.fullscreen);
+        assert!(client.has_state(WindowState::Fullscreen));
+        client.toggle_fullscreen();
+        assert!(!client.has_state(WindowState::Fullscreen));
+        assert!(client.has_state(WindowState::Above));
+        let value = serde_json::to_value(&client).unwrap();
+        assert_eq!(value["states"], serde_json::json!(["Above"]));
+    }
+
+    #[test]
+    fn expected_unmaps() {
+        let mut client = Client::new(WindowGeometry::default(), 0, 0);
+        // Windows that were never mapped do not generate an UnmapNotify.
+        client.set_mapped(false);
+        assert!(!client.take_expected_unmap());
+        client.set_mapped(true);
+        client.set_mapped(false);
+        client.set_mapped(false);
+        client.set_mapped(true);
+        client.set_mapped(false);
+        assert!(client.take_expected_unmap());
+        assert!(client.take_expected_unmap());
+        // Any further unmap is made by the client.
+        assert!(!client.take_expected_unmap());
+    }
+
+    #[test]
+    fn stable_ids() {
+        let mut clients = Clients::default();
+        let ids: Vec<_> = (0..3)
+            .map(|workspace| {
+                clients.insert(Client::new(
+                    WindowGeometry::new(0, 0, 100, 100, 1),
+                    0,
+                    workspace,
+                ))
+            })
+            .collect();
+        assert!(clients.remove(ids[0]).is_some());
+        // Removing a client does not change the ids of the others.
+        assert_eq!(clients[ids[2]].workspace, 2);
+        assert_eq!(clients.ids(), vec![ids[1], ids[2]]);
+        assert!(!clients.contains(ids[0]));
+        // Ids are not reused.
+        let id = clients.insert(Client::new(WindowGeometry::default(), 0, 0));
+        assert!(!ids.contains(&id));
+        let value = serde_json::to_value(&clients).unwrap();
+        assert_eq!(value[2]["id"], serde_json::json!(id));
+    }
+
+    #[test]
+    fn stacking_order() {
+        let mut clients = Clients::default();
+        let ids: Vec<_> = (0..3)
+            .map(|_| clients.insert(Client::new(WindowGeometry::default(), 0, 0)))
+            .collect();
+        let stacking = |clients: &Clients| clients.stacking().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(stacking(&clients), ids);
+        clients.raise(ids[0]);
+        assert_eq!(stacking(&clients), vec![ids[1], ids[2], ids[0]]);
+        clients.place_above(ids[2], ids[1]);
+        assert_eq!(stacking(&clients), vec![ids[1], ids[2], ids[0]]);
+        clients.place_above(ids[1], ids[0]);
+        assert_eq!(stacking(&clients), vec![ids[2], ids[0], ids[1]]);
+        clients.remove(ids[0]);
+        assert_eq!(stacking(&clients), vec![ids[2], ids[1]]);
+        // The order in which clients were added is not changed by stacking.
+        assert_eq!(clients.ids(), vec![ids[1], ids[2]]);
+    }
+
+    #[test]
+    fn restacking() {
+        let mut clients = Clients::default();
+        let ids: Vec<_> = (1..=4)
+            .map(|window| {
+                let mut client = Client::new(WindowGeometry::default(), 0, 0);
+                client.window = window;
+                if window == 2 {
+                    client.set_state(WindowState::Above, true);
+                }
+                if window >= 3 {
+                    client.transient_for = Some(1);
+                }
+                if window == 4 {
+                    client.workspace = 1;
+                }
+                clients.insert(client)
+            })
+            .collect();
+        // The transient on another workspace is not shown.
+        let (above, transients) = clients.restacking(|client| client.workspace == 0);
+        assert_eq!(above, vec![ids[1]]);
+        assert_eq!(transients, vec![(ids[2], ids[0])]);
+    }
+
+    #[test]
+    fn find_window() {
+        let mut clients = Clients::default();
+        let ids: Vec<_> = (1..=3)
+            .map(|window| {
+                let mut client = Client::new(WindowGeometry::default(), 0, 0);
+                client.window = window;
+                clients.insert(client)
+            })
+            .collect();
+        assert_eq!(clients.find(2), Some(ids[1]));
+        clients.remove(ids[1]);
+        assert_eq!(clients.find(2), None);
+        assert_eq!(clients.find(3), Some(ids[2]));
+        assert_eq!(clients.find(4), None);
+        let mut event: xlib::XEvent = unsafe { mem::zeroed() };
+        event.property = xlib::XPropertyEvent {
+            type_: xlib::PropertyNotify,
+            window: 3,
+            ..unsafe { mem::zeroed() }
+        };
+        assert_eq!(clients.event_client(&event), Some(ids[2]));
+        event.any.type_ = xlib::KeyPress;
+        assert_eq!(clients.event_client(&event), None);
+    }
+
+    #[test]
+    fn last_on_workspace() {
+        let mut clients = Clients::default();
+        let first = clients.insert(Client::new(WindowGeometry::default(), 0, 0));
+        let second_monitor = clients.insert(Client::new(WindowGeometry::default(), 1, 0));
+        let hidden_workspace = clients.insert(Client::new(WindowGeometry::default(), 0, 1));
+        let mut hidden = Client::new(WindowGeometry::default(), 0, 0);
+        hidden.set_state(WindowState::Hidden, true);
+        clients.insert(hidden);
+        // Neither the client on the other monitor nor the one on another workspace is picked.
+        assert_eq!(clients.last_on(0, 0), Some(first));
+        assert_eq!(clients.last_on(1, 0), Some(second_monitor));
+        assert_eq!(clients.last_on(0, 1), Some(hidden_workspace));
+        clients.remove(second_monitor);
+        assert_eq!(clients.last_on(1, 0), None);
+        assert_eq!(clients.last_on(0, 0), Some(first));
     }
 }
@@ -1,60 +1,239 @@
-use crate::backend::Backend;
-use std::{ffi::CString, mem};
+use crate::{
+    backend::{
+        client::ClientId,
+        monitor::{Margins, Strut},
+        Backend,
+    },
+    rules::WindowProperties,
+};
+use std::{ffi::CStr, mem, os::raw::c_char, slice};
 use x11_dl::xlib;
 
+// States of WM_HINTS and WM_STATE from Xutil.h, which x11-dl does not export. Clients that are
+// not shown are iconic, so that they are adopted again after a restart.
+pub const WITHDRAWN_STATE: i32 = 0;
+pub const NORMAL_STATE: i32 = 1;
+pub const ICONIC_STATE: i32 = 3;
+
 impl Backend<'_> {
+    // Number of 32 bit values read from list properties.
+    const MAX_PROP_LENGTH: i64 = 1024;
+
     pub fn set_hints(&self) {
         // Set WM name.
         let wm_name = "critwm";
-        self.set_prop_string(self.atoms.net_wm_name, wm_name);
-        self.set_prop_u64(
+        self.set_prop_string(self.root, self.atoms.net_wm_name, wm_name);
+        self.set_prop_longs(
+            self.root,
             self.atoms.net_supporting_wm_check,
             xlib::XA_WINDOW,
-            self.root,
+            &[self.root],
         );
         // Set supported net atoms.
         // Makes `$ xprop -root _NET_SUPPORTED` list supported net atoms.
-        let net_supported = self.atoms.net_supported();
-        let net_supported_ptr: *const xlib::Atom = net_supported.as_ptr();
-        self.set_prop_u8_ptr(
+        self.set_prop_longs(
+            self.root,
             self.atoms.net_supported,
             xlib::XA_ATOM,
-            net_supported.len() as i32,
-            net_supported_ptr.cast::<u8>(),
+            &self.atoms.net_supported(),
         );
     }
 
-    pub fn set_window_state(&self, window: xlib::Window, atom: xlib::Atom) {
-        let data = vec![atom as u32];
-        unsafe {
-            (self.xlib.XChangeProperty)(
-                self.display,
-                window,
-                self.atoms.net_wm_state,
-                xlib::XA_ATOM,
-                32,
-                xlib::PropModeReplace,
-                data.as_ptr().cast::<u8>(),
-                data.len() as i32,
-            );
+    // Publish the workspaces so that pagers and bars can display them.
+    pub fn set_desktop_hints(&self) {
+        let count = self.settings.workspaces.len();
+        self.set_prop_longs(
+            self.root,
+            self.atoms.net_number_of_desktops,
+            xlib::XA_CARDINAL,
+            &[count as u64],
+        );
+        // Workspaces are not larger than the screen, so every viewport is at the origin.
+        self.set_prop_longs(
+            self.root,
+            self.atoms.net_desktop_viewport,
+            xlib::XA_CARDINAL,
+            &vec![0; count * 2],
+        );
+        self.set_desktop_names();
+        self.set_current_desktop();
+    }
+
+    pub fn set_desktop_names(&self) {
+        // Names are null terminated and concatenated.
+        let names: String = self
+            .settings
+            .workspaces
+            .iter()
+            .map(|name| format!("{}\0", name))
+            .collect();
+        self.set_prop_string(self.root, self.atoms.net_desktop_names, &names);
+    }
+
+    // The current desktop is the current workspace of the focused monitor.
+    pub fn set_current_desktop(&self) {
+        let workspace = self.monitors[self.current_monitor].get_current_workspace();
+        self.set_prop_longs(
+            self.root,
+            self.atoms.net_current_desktop,
+            xlib::XA_CARDINAL,
+            &[workspace as u64],
+        );
+    }
+
+    // The work area of every desktop is the screen without the space reserved by docks.
+    pub fn set_workarea(&self, margins: &Margins) {
+        let (screen_width, screen_height) = self.screen_size();
+        let area = [
+            margins.left as u64,
+            margins.top as u64,
+            (screen_width - margins.left - margins.right) as u64,
+            (screen_height - margins.top - margins.bottom) as u64,
+        ];
+        let workarea: Vec<u64> = area
+            .iter()
+            .cycle()
+            .take(area.len() * self.settings.workspaces.len())
+            .copied()
+            .collect();
+        self.set_prop_longs(
+            self.root,
+            self.atoms.net_workarea,
+            xlib::XA_CARDINAL,
+            &workarea,
+        );
+    }
+
+    pub fn set_client_desktop(&self, id: ClientId) {
+        let client = &self.clients[id];
+        self.set_prop_longs(
+            client.window,
+            self.atoms.net_wm_desktop,
+            xlib::XA_CARDINAL,
+            &[client.workspace as u64],
+        );
+    }
+
+    // Publish managed windows in the order they were mapped, followed by the stacking order.
+    pub fn set_client_list(&self) {
+        let windows: Vec<xlib::Window> = self.clients.iter().map(|client| client.window).collect();
+        self.set_prop_longs(
+            self.root,
+            self.atoms.net_client_list,
+            xlib::XA_WINDOW,
+            &windows,
+        );
+        self.set_client_list_stacking();
+    }
+
+    // Managed windows from the bottom of the stack to the top.
+    pub fn set_client_list_stacking(&self) {
+        let windows: Vec<xlib::Window> = self
+            .clients
+            .stacking()
+            .map(|client| client.window)
+            .collect();
+        self.set_prop_longs(
+            self.root,
+            self.atoms.net_client_list_stacking,
+            xlib::XA_WINDOW,
+            &windows,
+        );
+    }
+
+    // A window of 0 means that no client is focused.
+    pub fn set_active_window(&self, window: xlib::Window) {
+        self.set_prop_longs(
+            self.root,
+            self.atoms.net_active_window,
+            xlib::XA_WINDOW,
+            &[window],
+        );
+    }
+
+    // Remove the client lists so that stale windows are not left behind after quitting.
+    pub fn clear_client_lists(&self) {
+        for atom in [
+            self.atoms.net_client_list,
+            self.atoms.net_client_list_stacking,
+            self.atoms.net_active_window,
+        ] {
+            unsafe { (self.xlib.XDeleteProperty)(self.display, self.root, atom) };
+        }
+    }
+
+    // WM_STATE holds the state of a window followed by its icon window, which critwm never sets.
+    pub fn set_wm_state(&self, window: xlib::Window, state: i32) {
+        self.set_prop_longs(
+            window,
+            self.atoms.wm_state,
+            self.atoms.wm_state,
+            &[state as u64, 0],
+        );
+    }
+
+    pub fn get_wm_state(&self, window: xlib::Window) -> Option<i32> {
+        self.get_long_prop(window, self.atoms.wm_state, self.atoms.wm_state)
+            .map(|state| state as i32)
+    }
+
+    // Whether WM_HINTS asks for the window to start iconic.
+    pub fn starts_iconic(&self, window: xlib::Window) -> bool {
+        let hints = unsafe { (self.xlib.XGetWMHints)(self.display, window) };
+        if hints.is_null() {
+            return false;
         }
+        let wm_hints = unsafe { &*hints };
+        let iconic =
+            wm_hints.flags & xlib::StateHint != 0 && wm_hints.initial_state == ICONIC_STATE;
+        unsafe { (self.xlib.XFree)(hints.cast()) };
+        iconic
+    }
+
+    // Write every state of a client to its _NET_WM_STATE.
+    pub fn set_window_states(&self, id: ClientId) {
+        let client = &self.clients[id];
+        let atoms: Vec<xlib::Atom> = client
+            .states
+            .iter()
+            .map(|state| self.atoms.state_atom(*state))
+            .collect();
+        self.set_prop_longs(
+            client.window,
+            self.atoms.net_wm_state,
+            xlib::XA_ATOM,
+            &atoms,
+        );
     }
 
     pub fn get_atom_prop(&self, window: xlib::Window, prop: xlib::Atom) -> Option<xlib::Atom> {
+        self.get_long_prop(window, prop, xlib::XA_ATOM)
+    }
+
+    // Get every atom of a property holding a list of atoms.
+    pub fn get_atom_props(&self, window: xlib::Window, prop: xlib::Atom) -> Vec<xlib::Atom> {
+        self.get_long_props(window, prop, xlib::XA_ATOM)
+    }
+
+    pub fn get_cardinal_props(&self, window: xlib::Window, prop: xlib::Atom) -> Vec<u64> {
+        self.get_long_props(window, prop, xlib::XA_CARDINAL)
+    }
+
+    fn get_long_props(&self, window: xlib::Window, prop: xlib::Atom, type_: u64) -> Vec<u64> {
         let mut type_return = 0;
         let mut format_return = 0;
         let mut nitems_return = 0;
         let mut bytes_after_return = 0;
-        let mut prop_return = unsafe { mem::zeroed() };
+        let mut prop_return: *mut u8 = unsafe { mem::zeroed() };
         let status = unsafe {
             (self.xlib.XGetWindowProperty)(
                 self.display,
                 window,
                 prop,
                 0,
-                mem::size_of::<xlib::Atom>() as i64,
+                Self::MAX_PROP_LENGTH,
                 0,
-                xlib::XA_ATOM,
+                type_,
                 &mut type_return,
                 &mut format_return,
                 &mut nitems_return,
@@ -62,50 +241,121 @@ impl Backend<'_> {
                 &mut prop_return,
             )
         };
-        Self::get_prop(status, prop_return)
-    }
-
-    fn set_prop_string(&self, atom: xlib::Atom, value: &str) {
-        if let Ok(cstring) = CString::new(value) {
-            unsafe {
-                (self.xlib.XChangeProperty)(
-                    self.display,
-                    self.root,
-                    atom,
-                    xlib::XA_CARDINAL,
-                    8,
-                    xlib::PropModeReplace,
-                    cstring.as_ptr().cast::<u8>(),
-                    value.len() as i32,
+        if status != i32::from(xlib::Success) || prop_return.is_null() {
+            return Vec::new();
+        }
+        // Values with a format of 32 are returned as longs. Clients may set properties of any
+        // format and type, which are not read.
+        let values = if format_return == 32 && type_return == type_ {
+            unsafe { slice::from_raw_parts(prop_return as *const u64, nitems_return as usize) }
+                .to_vec()
+        } else {
+            Vec::new()
+        };
+        unsafe { (self.xlib.XFree)(prop_return.cast()) };
+        values
+    }
+
+    // Get the strut of a dock, preferring _NET_WM_STRUT_PARTIAL over _NET_WM_STRUT.
+    pub fn get_strut(&self, window: xlib::Window) -> Option<Strut> {
+        Strut::from_partial(&self.get_cardinal_props(window, self.atoms.net_wm_strut_partial))
+            .or_else(|| {
+                let (screen_width, screen_height) = self.screen_size();
+                Strut::from_sizes(
+                    &self.get_cardinal_props(window, self.atoms.net_wm_strut),
+                    screen_width,
+                    screen_height,
                 )
-            };
+            })
+    }
+
+    pub fn get_cardinal_prop(&self, window: xlib::Window, prop: xlib::Atom) -> Option<u64> {
+        self.get_long_prop(window, prop, xlib::XA_CARDINAL)
+    }
+
+    fn get_long_prop(&self, window: xlib::Window, prop: xlib::Atom, type_: u64) -> Option<u64> {
+        self.get_long_props(window, prop, type_).first().copied()
+    }
+
+    // Read the properties of a window that rules are matched against.
+    pub fn get_window_properties(&self, window: xlib::Window) -> WindowProperties {
+        let (instance, class) = self.get_class_hint(window);
+        WindowProperties {
+            class,
+            instance,
+            title: self
+                .get_text_prop(window, self.atoms.net_wm_name)
+                .or_else(|| self.get_text_prop(window, xlib::XA_WM_NAME)),
+            role: self.get_text_prop(window, self.atoms.wm_window_role),
+        }
+    }
+
+    pub fn get_pid(&self, window: xlib::Window) -> Option<u32> {
+        self.get_cardinal_prop(window, self.atoms.net_wm_pid)
+            .map(|pid| pid as u32)
+    }
+
+    // Get the instance and class of a window from WM_CLASS.
+    fn get_class_hint(&self, window: xlib::Window) -> (Option<String>, Option<String>) {
+        let mut hint: xlib::XClassHint = unsafe { mem::zeroed() };
+        if unsafe { (self.xlib.XGetClassHint)(self.display, window, &mut hint) } == 0 {
+            return (None, None);
+        }
+        let take = |value: *mut c_char| {
+            if value.is_null() {
+                return None;
+            }
+            let string = unsafe { CStr::from_ptr(value) }
+                .to_string_lossy()
+                .into_owned();
+            unsafe { (self.xlib.XFree)(value.cast()) };
+            Some(string)
+        };
+        (take(hint.res_name), take(hint.res_class))
+    }
+
+    pub fn get_text_prop(&self, window: xlib::Window, atom: xlib::Atom) -> Option<String> {
+        let mut text: xlib::XTextProperty = unsafe { mem::zeroed() };
+        let status = unsafe { (self.xlib.XGetTextProperty)(self.display, window, &mut text, atom) };
+        if status == 0 || text.value.is_null() {
+            return None;
         }
+        let bytes = unsafe { slice::from_raw_parts(text.value, text.nitems as usize) };
+        let value = String::from_utf8_lossy(bytes)
+            .trim_end_matches('\0')
+            .to_owned();
+        unsafe { (self.xlib.XFree)(text.value.cast()) };
+        Some(value)
     }
 
-    fn set_prop_u64(&self, atom: xlib::Atom, type_: u64, value: u64) {
-        self.set_prop_u8_ptr(atom, type_, 1_i32, vec![value as u32].as_ptr().cast::<u8>());
+    pub fn set_prop_string(&self, window: xlib::Window, atom: xlib::Atom, value: &str) {
+        unsafe {
+            (self.xlib.XChangeProperty)(
+                self.display,
+                window,
+                atom,
+                self.atoms.utf8_string,
+                8,
+                xlib::PropModeReplace,
+                value.as_ptr(),
+                value.len() as i32,
+            )
+        };
     }
 
-    fn set_prop_u8_ptr(&self, atom: xlib::Atom, type_: u64, len: i32, value: *const u8) {
+    // Properties with a format of 32 are passed to Xlib as longs.
+    fn set_prop_longs(&self, window: xlib::Window, atom: xlib::Atom, type_: u64, values: &[u64]) {
         unsafe {
             (self.xlib.XChangeProperty)(
                 self.display,
-                self.root,
+                window,
                 atom,
                 type_,
                 32,
                 xlib::PropModeReplace,
-                value,
-                len,
+                values.as_ptr().cast::<u8>(),
+                values.len() as i32,
             );
         };
     }
-
-    fn get_prop(status: i32, prop_return: *mut u8) -> Option<xlib::Atom> {
-        if status == i32::from(xlib::Success) && !prop_return.is_null() {
-            Some(unsafe { *(prop_return as *const xlib::Atom) })
-        } else {
-            None
-        }
-    }
 }
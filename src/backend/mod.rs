@@ -3,52 +3,85 @@ mod atom;
 pub mod client;
 mod hints;
 pub mod monitor;
+mod randr;
+pub mod session;
 pub mod signal;
 
 use crate::{
     config,
     error::CritResult,
-    layouts::Layout,
-    util::{Action, Cursor, Key, XCursor, XCursorShape},
+    rules::RuleActions,
+    settings::Settings,
+    util::{self, Cursor, Key, ModMask, XCursor, XCursorShape},
 };
 use atom::Atom;
-use client::Client;
-use monitor::Monitor;
-use std::{cmp, collections::HashMap, mem, slice};
-use x11_dl::{xinerama, xlib};
+use client::{Client, ClientId, Clients, WindowState};
+use monitor::{Margins, Monitor, Strut};
+use session::Session;
+use std::{cmp, mem, os::unix::io::RawFd, path::PathBuf, slice};
+use x11_dl::{keysym, xinerama, xlib, xrandr};
 
 pub struct Backend<'a> {
     xlib: &'a xlib::Xlib,
-    xinerama_xlib: &'a xinerama::Xlib,
+    // Xinerama is used to find monitors without RandR if it is active.
+    xinerama_xlib: Option<&'a xinerama::Xlib>,
+    // RandR is used to find monitors and their changes if the library is available.
+    xrandr: Option<&'a xrandr::Xrandr>,
+    // First event of the RandR extension, which is None if it is not supported by the server.
+    randr_event_base: Option<i32>,
     display: *mut xlib::Display,
     root: xlib::Window,
+    // Size of the root window, which Xlib does not update when the screen is resized.
+    screen_size: (i32, i32),
     start: xlib::XButtonEvent,
     attrs: xlib::XWindowAttributes,
     previous_mouse_position: (i32, i32),
     atoms: Atom,
     cursor: Cursor,
-    key_map: HashMap<Key, Action>,
-    clients: Vec<Client>,
-    current_client: Option<usize>,
+    // Modifiers of NumLock and ScrollLock, found from the modifier mapping.
+    numlock_mask: ModMask,
+    scrolllock_mask: ModMask,
+    settings: Settings,
+    // Configuration file that settings are reloaded from.
+    settings_path: Option<PathBuf>,
+    clients: Clients,
+    current_client: Option<ClientId>,
     monitors: Vec<Monitor<{ config::WORKSPACE_COUNT }>>,
-    layouts: Vec<Layout>,
+    // Monitors whose output has been disconnected, kept until it is connected again.
+    disconnected_monitors: Vec<Monitor<{ config::WORKSPACE_COUNT }>>,
     current_monitor: usize,
+    // Set once the session is saved for a new instance that replaces this one.
+    restarting: bool,
 }
 
 impl<'a> Backend<'a> {
     const POINTER_BUTTON_MASK: u32 =
         (xlib::PointerMotionMask | xlib::ButtonPressMask | xlib::ButtonReleaseMask) as u32;
+    // Mode for XEventsQueued that only counts events already read from the connection.
+    const QUEUED_ALREADY: i32 = 0;
+    // Actions of a _NET_WM_STATE client message.
+    const NET_WM_STATE_REMOVE: i64 = 0;
+    const NET_WM_STATE_ADD: i64 = 1;
+    const NET_WM_STATE_TOGGLE: i64 = 2;
 
     /// # Safety
     ///
     /// This function should not be called before x11 has started.
     pub unsafe fn new(
         xlib: &'a xlib::Xlib,
-        xinerama_xlib: &'a xinerama::Xlib,
+        xinerama_xlib: Option<&'a xinerama::Xlib>,
+        xrandr: Option<&'a xrandr::Xrandr>,
         display: *mut xlib::Display,
+        settings: Settings,
+        settings_path: Option<PathBuf>,
     ) -> CritResult<Self> {
         // Get root window.
         let root = (xlib.XDefaultRootWindow)(display);
+        let screen = (xlib.XDefaultScreen)(display);
+        let screen_size = (
+            (xlib.XDisplayWidth)(display, screen),
+            (xlib.XDisplayHeight)(display, screen),
+        );
         (xlib.XSelectInput)(
             display,
             root,
@@ -69,45 +102,109 @@ impl<'a> Backend<'a> {
         Ok(Self {
             xlib,
             xinerama_xlib,
+            xrandr,
+            randr_event_base: None,
             display,
             root,
+            screen_size,
             start: mem::zeroed(),
             attrs: mem::zeroed(),
             previous_mouse_position: (0, 0),
             atoms,
             cursor,
-            key_map: config::get_keymap(),
-            clients: Vec::new(),
+            numlock_mask: 0,
+            scrolllock_mask: 0,
+            settings,
+            settings_path,
+            clients: Clients::default(),
             // current_client as None means that no client is focused.
             current_client: None,
             monitors: Vec::new(),
-            layouts: config::get_layouts(),
+            disconnected_monitors: Vec::new(),
             current_monitor: 0,
+            restarting: false,
         })
     }
 
-    pub fn initialize(&mut self) -> CritResult<()> {
+    // restarted is set if this instance replaces one that saved its session.
+    pub fn initialize(&mut self, restarted: bool) -> CritResult<()> {
         self.set_hints();
         self.set_cursor(self.cursor.norm);
-        self.fetch_monitors()?;
-        self.scan();
+        self.update_lock_masks();
+        let session = self.take_session(restarted);
+        self.select_randr_input();
+        self.fetch_monitors();
+        if let Some(session) = &session {
+            self.restore_monitors(session);
+        }
+        self.set_desktop_hints();
+        self.update_struts();
+        self.scan(session.as_ref());
         Ok(())
     }
 
+    // Find which modifiers NumLock and ScrollLock are mapped to.
+    fn update_lock_masks(&mut self) {
+        let numlock =
+            unsafe { (self.xlib.XKeysymToKeycode)(self.display, keysym::XK_Num_Lock.into()) };
+        let scrolllock =
+            unsafe { (self.xlib.XKeysymToKeycode)(self.display, keysym::XK_Scroll_Lock.into()) };
+        self.numlock_mask = 0;
+        self.scrolllock_mask = 0;
+        let modmap = unsafe { (self.xlib.XGetModifierMapping)(self.display) };
+        if modmap.is_null() {
+            return;
+        }
+        let max_keypermod = unsafe { (*modmap).max_keypermod } as usize;
+        let keycodes = unsafe { slice::from_raw_parts((*modmap).modifiermap, 8 * max_keypermod) };
+        for (i, modifier_keycodes) in keycodes.chunks(max_keypermod.max(1)).enumerate() {
+            for keycode in modifier_keycodes.iter().filter(|keycode| **keycode != 0) {
+                if *keycode == numlock {
+                    self.numlock_mask = 1 << i;
+                } else if *keycode == scrolllock {
+                    self.scrolllock_mask = 1 << i;
+                }
+            }
+        }
+        unsafe { (self.xlib.XFreeModifiermap)(modmap) };
+    }
+
+    // Every combination of lock modifiers. Keys and buttons are grabbed with each of them so that
+    // bindings work regardless of which locks are on.
+    fn lock_combinations(&self) -> Vec<ModMask> {
+        let locks = [xlib::LockMask, self.numlock_mask, self.scrolllock_mask];
+        let mut combinations: Vec<ModMask> = (0..1 << locks.len())
+            .map(|bits: usize| {
+                locks
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| bits & (1 << i) != 0)
+                    .fold(0, |mask, (_, lock)| mask | lock)
+            })
+            .collect();
+        // Locks that are not mapped to a modifier produce duplicate combinations.
+        combinations.sort_unstable();
+        combinations.dedup();
+        combinations
+    }
+
     pub fn grab_keys(&self) {
         unsafe { (self.xlib.XUngrabKey)(self.display, xlib::AnyKey, xlib::AnyModifier, self.root) };
-        for key in self.key_map.keys() {
+        let lock_combinations = self.lock_combinations();
+        for key in self.settings.key_map.keys() {
             let code = unsafe { (self.xlib.XKeysymToKeycode)(self.display, key.sym) };
-            unsafe {
-                (self.xlib.XGrabKey)(
-                    self.display,
-                    i32::from(code),
-                    key.modifier,
-                    self.root,
-                    1,
-                    xlib::GrabModeAsync,
-                    xlib::GrabModeAsync,
-                );
+            for locks in &lock_combinations {
+                unsafe {
+                    (self.xlib.XGrabKey)(
+                        self.display,
+                        i32::from(code),
+                        key.modifier | locks,
+                        self.root,
+                        1,
+                        xlib::GrabModeAsync,
+                        xlib::GrabModeAsync,
+                    );
+                }
             }
         }
     }
@@ -121,19 +218,24 @@ impl<'a> Backend<'a> {
                 self.root,
             )
         };
-        let grab_button = |button: u32| unsafe {
-            (self.xlib.XGrabButton)(
-                self.display,
-                button,
-                config::MODKEY,
-                self.root,
-                0,
-                Self::POINTER_BUTTON_MASK,
-                xlib::GrabModeAsync,
-                xlib::GrabModeAsync,
-                0,
-                0,
-            );
+        let lock_combinations = self.lock_combinations();
+        let grab_button = |button: u32| {
+            for locks in &lock_combinations {
+                unsafe {
+                    (self.xlib.XGrabButton)(
+                        self.display,
+                        button,
+                        self.settings.modkey | locks,
+                        self.root,
+                        0,
+                        Self::POINTER_BUTTON_MASK,
+                        xlib::GrabModeAsync,
+                        xlib::GrabModeAsync,
+                        0,
+                        0,
+                    );
+                }
+            }
         };
         grab_button(xlib::Button1Mask);
         grab_button(xlib::Button3Mask);
@@ -173,6 +275,7 @@ impl<'a> Backend<'a> {
                 })
             {
                 self.current_monitor = monitor_index;
+                self.set_current_desktop();
                 // Ensure that subwindow is 0.
                 if child_return == 0 {
                     // Cursor has entered a new monitor but is not over any clients.
@@ -184,19 +287,48 @@ impl<'a> Backend<'a> {
         self.previous_mouse_position = (x, y);
     }
 
-    pub fn handle_event(&mut self) -> CritResult<()> {
-        // Handle events from xlib.
-        let mut event: xlib::XEvent = unsafe { mem::zeroed() };
-        unsafe { (self.xlib.XNextEvent)(self.display, &mut event) };
+    // File descriptor of the connection to the X server. It becomes readable when new events
+    // arrive.
+    pub fn connection_fd(&self) -> RawFd {
+        unsafe { (self.xlib.XConnectionNumber)(self.display) }
+    }
+
+    // Returns true if events have been read from the connection but not yet handled.
+    pub fn has_queued_events(&self) -> bool {
+        unsafe { (self.xlib.XEventsQueued)(self.display, Self::QUEUED_ALREADY) > 0 }
+    }
+
+    // Flush requests so that they reach the X server before waiting for new events.
+    pub fn flush(&self) {
+        unsafe { (self.xlib.XFlush)(self.display) };
+    }
+
+    // Handle every event that is available without blocking.
+    pub fn handle_events(&mut self) -> CritResult<()> {
+        while unsafe { (self.xlib.XPending)(self.display) } > 0 {
+            let mut event: xlib::XEvent = unsafe { mem::zeroed() };
+            unsafe { (self.xlib.XNextEvent)(self.display, &mut event) };
+            self.handle_event(event)?;
+        }
+        Ok(())
+    }
+
+    fn handle_event(&mut self, mut event: xlib::XEvent) -> CritResult<()> {
         let event_type = event.get_type();
         trace!("New event: {:?}", event_type);
+        // Client the event refers to, if it is about a managed window.
+        let event_client = self.clients.event_client(&event);
         match event_type {
             xlib::KeyPress => {
                 let key_event = xlib::XKeyEvent::from(event);
                 let keysym = unsafe {
                     (self.xlib.XKeycodeToKeysym)(self.display, key_event.keycode as u8, 0)
                 };
-                if let Some(action) = self.key_map.get(&Key::new(key_event.state, keysym)) {
+                let modifier = util::clean_mask(
+                    key_event.state,
+                    xlib::LockMask | self.numlock_mask | self.scrolllock_mask,
+                );
+                if let Some(action) = self.settings.key_map.get(&Key::new(modifier, keysym)) {
                     (action)();
                 }
             }
@@ -207,60 +339,52 @@ impl<'a> Backend<'a> {
                         event.button.subwindow,
                         &mut self.attrs,
                     );
-                    (self.xlib.XRaiseWindow)(self.display, event.button.subwindow);
                 };
+                match self.clients.find(unsafe { event.button.subwindow }) {
+                    Some(id) => self.raise_client(id),
+                    None => unsafe {
+                        (self.xlib.XRaiseWindow)(self.display, event.button.subwindow);
+                    },
+                }
+                self.restack();
                 self.start = unsafe { event.button };
-                if let Some(client_index) = self
-                    .clients
-                    .iter()
-                    .position(|client| client.window == self.start.subwindow)
-                {
-                    self.clients[client_index].floating = true;
+                if let Some(id) = self.clients.find(self.start.subwindow) {
+                    self.clients[id].floating = true;
                     self.arrange(
                         self.current_monitor,
                         self.monitors[self.current_monitor].get_current_workspace(),
                     );
                 }
             }
-            xlib::MotionNotify => {
-                if self.start.subwindow != 0 {
-                    if let Some(current_client) = self.current_client {
-                        // Compress motion notify events.
-                        while unsafe {
-                            (self.xlib.XCheckTypedEvent)(
-                                self.display,
-                                xlib::MotionNotify,
-                                &mut event,
-                            )
-                        } > 0
-                        {}
-                        let diff = || unsafe {
-                            (
-                                event.button.x_root - self.start.x_root,
-                                event.button.y_root - self.start.y_root,
-                            )
-                        };
-                        match self.start.button {
-                            xlib::Button1 => {
-                                self.set_cursor(self.cursor.mov);
-                                let (dx, dy) = diff();
-                                self.move_client(
-                                    current_client,
-                                    self.attrs.x + dx,
-                                    self.attrs.y + dy,
-                                );
-                            }
-                            xlib::Button3 => {
-                                self.set_cursor(self.cursor.res);
-                                let (dw, dh) = diff();
-                                self.resize_client(
-                                    current_client,
-                                    self.attrs.width + dw,
-                                    self.attrs.height + dh,
-                                );
-                            }
-                            _ => {}
+            xlib::MotionNotify if self.start.subwindow != 0 => {
+                if let Some(current_client) = self.current_client {
+                    // Compress motion notify events.
+                    while unsafe {
+                        (self.xlib.XCheckTypedEvent)(self.display, xlib::MotionNotify, &mut event)
+                    } > 0
+                    {}
+                    let diff = || unsafe {
+                        (
+                            event.button.x_root - self.start.x_root,
+                            event.button.y_root - self.start.y_root,
+                        )
+                    };
+                    match self.start.button {
+                        xlib::Button1 => {
+                            self.set_cursor(self.cursor.mov);
+                            let (dx, dy) = diff();
+                            self.move_client(current_client, self.attrs.x + dx, self.attrs.y + dy);
+                        }
+                        xlib::Button3 => {
+                            self.set_cursor(self.cursor.res);
+                            let (dw, dh) = diff();
+                            self.resize_client(
+                                current_client,
+                                self.attrs.width + dw,
+                                self.attrs.height + dh,
+                            );
                         }
+                        _ => {}
                     }
                 }
             }
@@ -270,11 +394,7 @@ impl<'a> Backend<'a> {
             }
             xlib::ConfigureRequest => {
                 let request = unsafe { event.configure_request };
-                if let Some(client) = self
-                    .clients
-                    .iter()
-                    .find(|client| client.window == request.window)
-                {
+                if let Some(client) = event_client.and_then(|id| self.clients.get(id)) {
                     let geometry = client.get_geometry();
                     let mut configure_event: xlib::XEvent =
                         xlib::XConfigureEvent::into(xlib::XConfigureEvent {
@@ -321,15 +441,13 @@ impl<'a> Backend<'a> {
                     };
                 }
             }
-            xlib::ConfigureNotify => {
-                if unsafe { event.configure.window } == self.root {
-                    // Root has notified configure.
-                    self.fetch_monitors()?;
-                    // Update client monitors.
-                    for i in 0..self.clients.len() {
-                        self.set_client_monitor(i);
-                    }
+            xlib::ConfigureNotify if unsafe { event.configure.window } == self.root => {
+                // Root has notified configure, which is the only notification without RandR.
+                let configure = unsafe { event.configure };
+                if (configure.width, configure.height) != self.screen_size {
+                    self.resize_screen(configure.width, configure.height);
                 }
+                self.update_monitors();
             }
             xlib::MappingNotify => {
                 let mut mapping = unsafe { event.mapping };
@@ -338,6 +456,7 @@ impl<'a> Backend<'a> {
                 {
                     unsafe { (self.xlib.XRefreshKeyboardMapping)(&mut mapping) };
                 }
+                self.update_lock_masks();
                 self.grab_keys();
                 self.grab_buttons();
             }
@@ -348,18 +467,35 @@ impl<'a> Backend<'a> {
                     (self.xlib.XGetWindowAttributes)(self.display, window, &mut attrs);
                     (self.xlib.XRaiseWindow)(self.display, event.button.subwindow);
                 };
-                if attrs.override_redirect == 0
-                    && !self.clients.iter().any(|client| client.window == window)
-                {
-                    self.add_window(window);
-                    self.arrange(
-                        self.current_monitor,
-                        self.monitors[self.current_monitor].get_current_workspace(),
-                    );
-                    let index = self.clients.len() - 1;
-                    self.set_focus_and_warp(index);
-                    unsafe {
-                        (self.xlib.XMapWindow)(self.display, window);
+                if attrs.override_redirect == 0 && event_client.is_none() {
+                    let id = self.add_window(window);
+                    if self.starts_iconic(window) {
+                        self.clients[id].set_state(WindowState::Hidden, true);
+                        self.set_window_states(id);
+                    }
+                    let actions = self.apply_rules(id);
+                    let monitor = self.clients[id].monitor;
+                    let workspace = self.clients[id].workspace;
+                    let dock = self.clients[id].dock;
+                    if dock {
+                        // Docks may reserve space on any monitor.
+                        self.update_struts();
+                        self.arrange_all();
+                    } else {
+                        self.arrange(monitor, workspace);
+                    }
+                    if let Some(fullscreen) = actions.fullscreen {
+                        self.set_client_state(id, WindowState::Fullscreen, fullscreen);
+                    }
+                    // Windows placed on a hidden workspace are mapped when it is shown, and docks
+                    // are mapped when the bar of their monitor is shown.
+                    if self.is_shown(&self.clients[id]) {
+                        if monitor == self.current_monitor && actions.focus.unwrap_or(true) {
+                            self.set_focus_and_warp(id);
+                        }
+                        self.map_client(id);
+                    } else {
+                        self.set_wm_state(window, hints::ICONIC_STATE);
                     }
                 }
             }
@@ -369,108 +505,301 @@ impl<'a> Backend<'a> {
                     self.set_cursor(self.cursor.norm);
                     self.start.subwindow = 0;
                 }
+                if let Some(id) = event_client {
+                    // Unmaps that critwm did not make withdraw the window. Windows that are not
+                    // mapped are withdrawn with a synthetic UnmapNotify.
+                    if unmap_event.send_event != 0 || !self.clients[id].take_expected_unmap() {
+                        self.unmanage(id, false);
+                    }
+                }
             }
             xlib::EnterNotify => {
                 // Pointer has entered a new window.
                 // Iterate through all clients to find this window and focus it.
-                if let Some(client_index) = self
-                    .clients
-                    .iter()
-                    .position(|client| client.window == unsafe { event.crossing.window })
-                {
-                    self.set_focus(Some(client_index));
+                if let Some(id) = event_client {
+                    self.set_focus(Some(id));
                 }
             }
             xlib::DestroyNotify => {
                 // Get the window that should be destroyed.
-                if let Some((client_index, target_client)) = self
-                    .clients
-                    .iter()
-                    .enumerate()
-                    .find(|(_, client)| client.window == unsafe { event.destroy_window.window })
-                {
-                    let workspace = self.monitors[target_client.monitor].get_current_workspace();
-                    if let Some(client) =
-                        self.monitors[target_client.monitor].get_last_selected_client(workspace)
-                    {
-                        if client == client_index {
-                            // The last selected client was the client that is about to be deleted.
-                            // Ensure that it is set to None.
-                            self.monitors[target_client.monitor]
-                                .set_last_selected_client(workspace, None);
-                        }
-                    }
-                    // Remove destroyed client.
-                    self.clients.remove(client_index);
-                    // The clients vector has shifted, update the indices for the last selected
-                    // clients for all monitors.
-                    for monitor in self.monitors.iter_mut() {
-                        for workspace in 0..config::WORKSPACE_COUNT {
-                            let last_selected_client = monitor.get_last_selected_client(workspace);
-                            if let Some(last_selected_client) = last_selected_client {
-                                if last_selected_client > client_index {
-                                    // One client has been removed, shift down the index by one.
-                                    monitor.set_last_selected_client(
-                                        workspace,
-                                        Some(last_selected_client - 1),
-                                    );
-                                }
-                            }
-                        }
-                    }
-                    if let Some(new_focus) = self
-                        .clients
-                        .iter()
-                        .rev()
-                        .position(|client| self.is_visible(workspace, client))
-                    {
-                        self.set_focus(Some(self.clients.len() - new_focus - 1));
-                    } else {
-                        self.set_focus(None);
-                    }
-                    self.arrange(self.current_monitor, workspace);
+                if let Some(id) = event_client {
+                    self.unmanage(id, true);
                 }
             }
             xlib::ClientMessage => {
                 let client_message = unsafe { event.client_message };
-                if client_message.message_type == self.atoms.net_wm_state {
-                    let data = client_message.data;
-                    if data.get_long(1) == self.atoms.net_wm_state_fullscreen as i64
-                        || data.get_long(2) == self.atoms.net_wm_state_fullscreen as i64
-                    {
-                        if let Some(index) = self
-                            .clients
-                            .iter()
-                            .position(|client| client.window == client_message.window)
-                        {
-                            self.toggle_fullscreen(index);
-                        }
-                    }
-                }
+                self.handle_client_message(&client_message);
             }
             xlib::PropertyNotify => {
                 let property_event = xlib::XPropertyEvent::from(event);
-                if let Some(client_index) = self
-                    .clients
-                    .iter()
-                    .position(|client| client.window == property_event.window)
-                {
-                    if property_event.atom == xlib::XA_WM_NORMAL_HINTS {
-                        self.update_size_hints(client_index);
-                        let (width, height) = self.apply_size_hints(client_index);
-                        self.clients[client_index].floating = true;
-                        self.resize_client(client_index, width, height);
+                if let Some(id) = event_client {
+                    if property_event.atom == xlib::XA_WM_HINTS {
+                        self.update_wm_hints(id);
+                        if self.current_client != Some(id) {
+                            self.set_window_border_color(id, self.unfocused_border_color(id));
+                        }
+                    } else if property_event.atom == xlib::XA_WM_NORMAL_HINTS {
+                        self.update_size_hints(id);
+                        let (width, height) = self.apply_size_hints(id);
+                        self.clients[id].floating = true;
+                        self.resize_client(id, width, height);
                     } else if property_event.atom == self.atoms.net_wm_window_type {
-                        self.update_window_type(client_index);
+                        self.update_window_type(id);
+                    } else if property_event.atom == xlib::XA_WM_NAME
+                        || property_event.atom == self.atoms.net_wm_name
+                        || property_event.atom == xlib::XA_WM_CLASS
+                        || property_event.atom == self.atoms.wm_window_role
+                    {
+                        let window = self.clients[id].window;
+                        self.clients[id].properties = self.get_window_properties(window);
+                    } else if (property_event.atom == self.atoms.net_wm_strut
+                        || property_event.atom == self.atoms.net_wm_strut_partial)
+                        && self.clients[id].dock
+                    {
+                        let window = self.clients[id].window;
+                        self.clients[id].strut = self.get_strut(window);
+                        self.update_struts();
+                        self.arrange_all();
+                    } else if property_event.atom == self.atoms.net_wm_pid {
+                        let window = self.clients[id].window;
+                        self.clients[id].pid = self.get_pid(window);
                     }
                 }
             }
+            _ if self.is_randr_event(event_type) => {
+                if let Some(xrandr) = self.xrandr {
+                    unsafe { (xrandr.XRRUpdateConfiguration)(&mut event) };
+                }
+                self.update_monitors();
+            }
             _ => {}
         }
         Ok(())
     }
 
-    fn scan(&mut self) {
+    // Handle requests from clients and tools such as pagers and taskbars.
+    fn handle_client_message(&mut self, client_message: &xlib::XClientMessageEvent) {
+        let data = client_message.data;
+        if client_message.message_type == self.atoms.net_current_desktop {
+            // The root window is the target, not a client.
+            let workspace = data.get_long(0) as usize;
+            if workspace < config::WORKSPACE_COUNT {
+                if let Err(e) = self.change_workspace(workspace) {
+                    error!("Failed to change to desktop {}: {}", workspace, e);
+                }
+            }
+            return;
+        }
+        let Some(id) = self.clients.find(client_message.window) else {
+            return;
+        };
+        if client_message.message_type == self.atoms.net_wm_state {
+            // Up to two states are changed by the same action.
+            for atom in [data.get_long(1), data.get_long(2)] {
+                if let Some(state) = self.atoms.window_state(atom as xlib::Atom) {
+                    let enabled = match data.get_long(0) {
+                        Self::NET_WM_STATE_REMOVE => false,
+                        Self::NET_WM_STATE_ADD => true,
+                        Self::NET_WM_STATE_TOGGLE => !self.clients[id].has_state(state),
+                        _ => continue,
+                    };
+                    self.set_client_state(id, state, enabled);
+                }
+            }
+        } else if client_message.message_type == self.atoms.net_active_window {
+            self.activate_client(id);
+        } else if client_message.message_type == self.atoms.net_close_window {
+            self.close_client(id);
+        } else if client_message.message_type == self.atoms.net_wm_desktop {
+            // Showing a window on all desktops is not supported.
+            let workspace = data.get_long(0) as usize;
+            if workspace < config::WORKSPACE_COUNT {
+                self.move_client_to_workspace(id, workspace);
+            }
+        } else if client_message.message_type == self.atoms.net_moveresize_window {
+            // Only floating clients can be placed, the layout places the others.
+            let client = &self.clients[id];
+            if client.floating && !client.fullscreen {
+                let geometry = client.get_geometry();
+                let border = geometry.border_width;
+                let flags = data.get_long(0) >> 8;
+                // Bits 8 to 11 of the first value tell which of x, y, width and height follow.
+                // Values that are not given keep their current value. The width and height exclude
+                // the border.
+                let value = |field: usize, current: i32| {
+                    if flags & (1 << field) != 0 {
+                        data.get_long(field + 1) as i32
+                    } else {
+                        current
+                    }
+                };
+                let x = value(0, geometry.x);
+                let y = value(1, geometry.y);
+                let width = value(2, geometry.width - border * 2) + border * 2;
+                let height = value(3, geometry.height - border * 2) + border * 2;
+                self.move_resize_client(id, x, y, width, height);
+            }
+        }
+    }
+
+    // Show the workspace of a client on its monitor and focus it.
+    fn activate_client(&mut self, id: ClientId) {
+        let monitor = self.clients[id].monitor;
+        if monitor != self.current_monitor {
+            self.current_monitor = monitor;
+            self.set_current_desktop();
+        }
+        if let Err(e) = self.change_workspace(self.clients[id].workspace) {
+            error!("Failed to change workspace: {}", e);
+        }
+        if self.clients[id].has_state(WindowState::Hidden) {
+            self.set_client_state(id, WindowState::Hidden, false);
+        }
+        self.raise_client(id);
+        self.restack();
+        self.set_focus_and_warp(id);
+    }
+
+    // Adopt the windows that are already shown, and those that were left iconic by a previous
+    // instance because they were on a hidden workspace.
+    // Clients in the session of a previous instance are restored as they were.
+    fn scan(&mut self, session: Option<&Session>) {
+        let windows: Vec<xlib::Window> = self
+            .query_tree()
+            .into_iter()
+            .filter(|window| {
+                let mut attrs: xlib::XWindowAttributes = unsafe { mem::zeroed() };
+                let status =
+                    unsafe { (self.xlib.XGetWindowAttributes)(self.display, *window, &mut attrs) };
+                status != 0
+                    && attrs.override_redirect == 0
+                    && (attrs.map_state == xlib::IsViewable
+                        || self.get_wm_state(*window) == Some(hints::ICONIC_STATE))
+            })
+            .collect();
+        info!("Queried {} windows", windows.len());
+        windows.iter().for_each(|window| {
+            let iconic = self.get_wm_state(*window) == Some(hints::ICONIC_STATE);
+            let id = self.add_window(*window);
+            // Restore the workspace the window was on.
+            match self
+                .get_cardinal_prop(*window, self.atoms.net_wm_desktop)
+                .map(|desktop| desktop as usize)
+                .filter(|desktop| *desktop < self.settings.workspaces.len())
+            {
+                Some(desktop) => {
+                    self.clients[id].workspace = desktop;
+                    self.set_client_desktop(id);
+                }
+                // Iconic windows without a workspace were minimized.
+                None if iconic => {
+                    self.clients[id].set_state(WindowState::Hidden, true);
+                    self.set_window_states(id);
+                }
+                None => {}
+            }
+            if let Some(saved) = session.and_then(|session| session.get_client(*window)) {
+                self.restore_client(id, saved);
+            }
+        });
+        self.update_struts();
+        self.arrange_all();
+        self.map_shown_clients();
+        let workspace = self.monitors[self.current_monitor].get_current_workspace();
+        let focus = session
+            .and_then(|session| {
+                session.focused_client(&self.clients, self.current_monitor, workspace)
+            })
+            .or_else(|| {
+                self.clients
+                    .iter()
+                    .find(|client| self.is_visible(workspace, client))
+                    .map(|client| client.id)
+            });
+        self.set_focus(focus);
+    }
+
+    // Map the clients on the current workspace of their monitor and the docks of monitors with a
+    // shown bar, unmapping every other client.
+    fn map_shown_clients(&mut self) {
+        for id in self.clients.ids() {
+            if self.is_shown(&self.clients[id]) {
+                self.map_client(id);
+            } else {
+                self.unmap_client(id);
+            }
+        }
+    }
+
+    // Map a client and mark it as normal in WM_STATE.
+    fn map_client(&mut self, id: ClientId) {
+        let client = &mut self.clients[id];
+        client.set_mapped(true);
+        let window = client.window;
+        unsafe { (self.xlib.XMapWindow)(self.display, window) };
+        self.set_wm_state(window, hints::NORMAL_STATE);
+    }
+
+    // Unmap a client and mark it as iconic in WM_STATE.
+    fn unmap_client(&mut self, id: ClientId) {
+        let client = &mut self.clients[id];
+        client.set_mapped(false);
+        let window = client.window;
+        unsafe { (self.xlib.XUnmapWindow)(self.display, window) };
+        self.set_wm_state(window, hints::ICONIC_STATE);
+    }
+
+    // Stop managing a client whose window was destroyed or withdrawn, and focus another client.
+    fn unmanage(&mut self, id: ClientId, destroyed: bool) {
+        if !destroyed {
+            self.withdraw(id);
+        }
+        let (monitor, workspace) = (self.clients[id].monitor, self.clients[id].workspace);
+        // The removed client can no longer be the last selected client of any workspace.
+        for monitor in self
+            .monitors
+            .iter_mut()
+            .chain(self.disconnected_monitors.iter_mut())
+        {
+            monitor.forget_client(id);
+        }
+        let dock = self.clients.remove(id).is_some_and(|client| client.dock);
+        self.set_client_list();
+        if dock {
+            self.update_struts();
+            self.arrange_all();
+        }
+        // Only move the focus away if it was on the removed client, and keep it on its monitor.
+        if self.current_client == Some(id) {
+            let current_workspace = self.monitors[monitor].get_current_workspace();
+            self.set_focus(self.clients.last_on(monitor, current_workspace));
+        }
+        self.arrange(monitor, workspace);
+    }
+
+    // Leave a withdrawn window as it was before it was managed, so that it is adopted anew if it
+    // is mapped again.
+    fn withdraw(&mut self, id: ClientId) {
+        let client = &self.clients[id];
+        let window = client.window;
+        let mut changes: xlib::XWindowChanges = unsafe { mem::zeroed() };
+        changes.border_width = client.original_border_width;
+        unsafe {
+            (self.xlib.XSelectInput)(self.display, window, xlib::NoEventMask);
+            (self.xlib.XConfigureWindow)(
+                self.display,
+                window,
+                xlib::CWBorderWidth as u32,
+                &mut changes,
+            );
+            (self.xlib.XDeleteProperty)(self.display, window, self.atoms.net_wm_state);
+            (self.xlib.XDeleteProperty)(self.display, window, self.atoms.net_wm_desktop);
+        }
+        self.set_wm_state(window, hints::WITHDRAWN_STATE);
+    }
+
+    // Children of the root window, from the bottom of the stack to the top.
+    fn query_tree(&self) -> Vec<xlib::Window> {
         let mut root_return = 0;
         let mut parent_return = 0;
         let mut array: *mut xlib::Window = unsafe { std::mem::zeroed() };
@@ -484,39 +813,17 @@ impl<'a> Backend<'a> {
                 &mut array,
                 &mut length,
             )
-        } != 0
+        } == 0
+            || array.is_null()
         {
-            let windows: Vec<&xlib::Window> =
-                unsafe { slice::from_raw_parts(array, length as usize) }
-                    .iter()
-                    .filter(|window| {
-                        let mut attrs: xlib::XWindowAttributes = unsafe { mem::zeroed() };
-                        let status = unsafe {
-                            (self.xlib.XGetWindowAttributes)(self.display, **window, &mut attrs)
-                        };
-                        status != 0
-                            && attrs.override_redirect == 0
-                            && attrs.map_state == xlib::IsViewable
-                    })
-                    .collect();
-            info!("Queried {} viewable windows", windows.len());
-            windows.iter().for_each(|window| self.add_window(**window));
-            self.arrange(
-                self.current_monitor,
-                self.monitors[self.current_monitor].get_current_workspace(),
-            );
-            self.set_focus(if !self.clients.is_empty() {
-                Some(0)
-            } else {
-                None
-            });
-            windows.iter().for_each(|window| unsafe {
-                (self.xlib.XMapWindow)(self.display, **window);
-            });
+            return Vec::new();
         }
+        let windows = unsafe { slice::from_raw_parts(array, length as usize) }.to_vec();
+        unsafe { (self.xlib.XFree)(array.cast()) };
+        windows
     }
 
-    fn add_window(&mut self, window: xlib::Window) {
+    fn add_window(&mut self, window: xlib::Window) -> ClientId {
         unsafe {
             (self.xlib.XSelectInput)(
                 self.display,
@@ -525,46 +832,194 @@ impl<'a> Backend<'a> {
             );
         };
         let workspace = self.monitors[self.current_monitor].get_current_workspace();
-        self.clients.push(Client::fetch(
+        let id = self.clients.insert(Client::fetch(
             self.xlib,
             self.display,
             window,
             self.current_monitor,
             workspace,
         ));
-        let index = self.clients.len() - 1;
-        self.update_window_type(index);
-        self.set_border(index, config::BORDER);
-        unsafe { (self.xlib.XSetWindowBorder)(self.display, window, config::BORDER_NORMAL_COLOR) };
+        // Windows that are adopted may already be mapped.
+        let mut attrs: xlib::XWindowAttributes = unsafe { mem::zeroed() };
+        if unsafe { (self.xlib.XGetWindowAttributes)(self.display, window, &mut attrs) } != 0 {
+            self.clients[id].set_mapped(attrs.map_state != xlib::IsUnmapped);
+        }
+        self.set_client_list();
+        self.update_window_type(id);
+        self.update_transient(id);
+        if self.clients[id].dock {
+            // Docks are not arranged, so their monitor is found from their position.
+            self.set_client_monitor(id);
+            self.clients[id].strut = self.get_strut(window);
+        }
+        self.update_window_states(id);
+        self.update_wm_hints(id);
+        self.clients[id].properties = self.get_window_properties(window);
+        self.clients[id].pid = self.get_pid(window);
+        self.set_client_desktop(id);
+        self.set_wm_state(window, hints::NORMAL_STATE);
+        self.set_border(id, self.settings.border);
+        self.set_window_border_color(id, self.unfocused_border_color(id));
+        id
+    }
+
+    // Apply the rules matching a new client, returning the actions that are left to apply once
+    // the client has been arranged.
+    fn apply_rules(&mut self, id: ClientId) -> RuleActions {
+        let actions = RuleActions::from_rules(&self.settings.rules, &self.clients[id].properties);
+        let previous_monitor = self.clients[id].monitor;
+        match actions.monitor {
+            Some(monitor) if monitor < self.monitors.len() => {
+                self.clients[id].monitor = monitor;
+                self.clients[id].workspace = self.monitors[monitor].get_current_workspace();
+            }
+            Some(monitor) => warn!(
+                "Rule places window on monitor {} which does not exist",
+                monitor
+            ),
+            None => {}
+        }
+        match actions.workspace {
+            // Compiled rules are not validated with the settings.
+            Some(workspace) if workspace < config::WORKSPACE_COUNT => {
+                self.clients[id].workspace = workspace;
+            }
+            Some(workspace) => warn!(
+                "Rule places window on workspace {} which does not exist",
+                workspace
+            ),
+            None => {}
+        }
+        let client = &mut self.clients[id];
+        // Giving a geometry makes the client floating unless it is explicitly tiled.
+        client.floating = actions
+            .floating
+            .unwrap_or(client.floating || actions.geometry.is_some());
+        if client.fullscreen {
+            // Windows mapped as fullscreen cover the monitor chosen by the rules.
+            let monitor = &self.monitors[client.monitor];
+            self.move_resize_client(
+                id,
+                monitor.get_x(),
+                monitor.get_y(),
+                monitor.get_width(),
+                monitor.get_height(),
+            );
+        } else if client.floating {
+            let monitor = &self.monitors[client.monitor];
+            let (x, y) = (monitor.get_x(), monitor.get_y());
+            if let Some(geometry) = &actions.geometry {
+                self.move_resize_client(
+                    id,
+                    x + geometry.x,
+                    y + geometry.y,
+                    geometry.width,
+                    geometry.height,
+                );
+            } else if client.monitor != previous_monitor {
+                // Keep the position of the client relative to its new monitor.
+                let previous_monitor = &self.monitors[previous_monitor];
+                let geometry = client.get_geometry();
+                let x = geometry.x - previous_monitor.get_x() + x;
+                let y = geometry.y - previous_monitor.get_y() + y;
+                self.move_client(id, x, y);
+            }
+        }
+        self.set_client_desktop(id);
+        actions
+    }
+
+    // Return if client is on the current workspace of its monitor, or is a dock of a monitor with
+    // a shown bar.
+    fn is_shown(&self, client: &Client) -> bool {
+        let monitor = &self.monitors[client.monitor];
+        if client.dock {
+            monitor.is_bar_shown()
+        } else {
+            client.workspace == monitor.get_current_workspace()
+                && !client.has_state(WindowState::Hidden)
+        }
     }
 
     // Return if client is visible in the current monitor in given workspace.
     fn is_visible(&self, workspace: usize, client: &Client) -> bool {
-        client.monitor == self.current_monitor && client.workspace == workspace && !client.dock
+        client.monitor == self.current_monitor
+            && client.workspace == workspace
+            && !client.dock
+            && !client.has_state(WindowState::Hidden)
     }
 
     fn set_cursor(&self, cursor: XCursor) {
         unsafe { (self.xlib.XDefineCursor)(self.display, self.root, cursor) };
     }
 
+    // Reserve the space of shown docks on each monitor and publish the resulting work area.
+    fn update_struts(&mut self) {
+        let (screen_width, screen_height) = self.screen_size();
+        let struts: Vec<&Strut> = self
+            .clients
+            .iter()
+            .filter(|client| client.dock && self.monitors[client.monitor].is_bar_shown())
+            .filter_map(|client| client.strut.as_ref())
+            .collect();
+        for monitor in self.monitors.iter_mut() {
+            let margins = struts.iter().fold(Margins::default(), |margins, strut| {
+                margins.max(&strut.margins(monitor.get_geometry(), screen_width, screen_height))
+            });
+            monitor.set_margins(margins);
+        }
+        let screen_margins = struts.iter().fold(Margins::default(), |margins, strut| {
+            margins.max(&strut.screen_margins())
+        });
+        self.set_workarea(&screen_margins);
+    }
+
+    fn screen_size(&self) -> (i32, i32) {
+        self.screen_size
+    }
+
+    // Struts and the work area depend on the size of the screen, so they are updated even if the
+    // monitors stay the same, such as when a RandR notification has already been handled.
+    fn resize_screen(&mut self, width: i32, height: i32) {
+        self.screen_size = (width, height);
+        for id in self.clients.ids() {
+            if self.clients[id].dock {
+                // Struts without ranges span the whole edge of the screen.
+                self.clients[id].strut = self.get_strut(self.clients[id].window);
+            }
+        }
+        self.update_struts();
+        self.arrange_all();
+    }
+
+    // Arrange every workspace of every monitor.
+    fn arrange_all(&mut self) {
+        for monitor in 0..self.monitors.len() {
+            for workspace in 0..config::WORKSPACE_COUNT {
+                self.arrange(monitor, workspace);
+            }
+        }
+    }
+
     fn arrange(&mut self, monitor: usize, workspace: usize) {
         let layout = self.monitors[monitor].get_layout();
-        for (index, geometry) in (layout.func)(
+        let clients: Vec<&Client> = self.clients.iter().collect();
+        let geometries = (layout.func)(
             monitor,
             workspace,
             self.monitors[monitor].get_geometry(),
-            &self.clients,
-            self.monitors[monitor].get_bar_status(),
-        )
-        .iter()
-        .enumerate()
-        {
-            if self.clients[index].floating {
-                unsafe { (self.xlib.XRaiseWindow)(self.display, self.clients[index].window) };
+            &clients,
+            self.monitors[monitor].get_margins(),
+            &self.settings,
+        );
+        // Geometries are returned in the order of the clients.
+        for (id, geometry) in self.clients.ids().into_iter().zip(&geometries) {
+            if self.clients[id].floating {
+                self.raise_client(id);
             }
-            if self.clients[index].get_geometry() != geometry {
+            if self.clients[id].get_geometry() != geometry {
                 self.move_resize_client(
-                    index,
+                    id,
                     geometry.x,
                     geometry.y,
                     geometry.width,
@@ -572,47 +1027,49 @@ impl<'a> Backend<'a> {
                 );
             }
         }
+        // Floating clients may have been raised above clients that should stay on top.
+        self.restack();
     }
 
-    fn move_resize_client(&mut self, index: usize, x: i32, y: i32, width: i32, height: i32) {
-        let border = self.clients[index].get_geometry().border_width;
-        self.set_border(index, border);
+    fn move_resize_client(&mut self, id: ClientId, x: i32, y: i32, width: i32, height: i32) {
+        let border = self.clients[id].get_geometry().border_width;
+        self.set_border(id, border);
         unsafe {
             (self.xlib.XMoveResizeWindow)(
                 self.display,
-                self.clients[index].window,
+                self.clients[id].window,
                 x,
                 y,
                 (width - border * 2) as u32,
                 (height - border * 2) as u32,
             )
         };
-        self.set_client_monitor(index);
+        self.set_client_monitor(id);
     }
 
-    fn move_client(&mut self, index: usize, x: i32, y: i32) {
-        unsafe { (self.xlib.XMoveWindow)(self.display, self.clients[index].window, x, y) };
-        self.set_client_monitor(index);
+    fn move_client(&mut self, id: ClientId, x: i32, y: i32) {
+        unsafe { (self.xlib.XMoveWindow)(self.display, self.clients[id].window, x, y) };
+        self.set_client_monitor(id);
     }
 
-    fn resize_client(&mut self, index: usize, width: i32, height: i32) {
-        let border = self.clients[index].get_geometry().border_width;
-        self.set_border(index, border);
+    fn resize_client(&mut self, id: ClientId, width: i32, height: i32) {
+        let border = self.clients[id].get_geometry().border_width;
+        self.set_border(id, border);
         unsafe {
             (self.xlib.XResizeWindow)(
                 self.display,
-                self.clients[index].window,
+                self.clients[id].window,
                 (width - border * 2) as u32,
                 (height - border * 2) as u32,
             )
         };
-        self.set_client_monitor(index);
+        self.set_client_monitor(id);
     }
 
-    fn update_size_hints(&mut self, index: usize) {
+    fn update_size_hints(&mut self, id: ClientId) {
         let mut supplied = 0;
         let mut size: xlib::XSizeHints = unsafe { mem::zeroed() };
-        let client = &mut self.clients[index];
+        let client = &mut self.clients[id];
         if unsafe {
             (self.xlib.XGetWMNormalHints)(self.display, client.window, &mut size, &mut supplied)
         } == 0
@@ -666,8 +1123,8 @@ impl<'a> Backend<'a> {
         }
     }
 
-    fn apply_size_hints(&self, index: usize) -> (i32, i32) {
-        let geometry = self.clients[index].get_geometry();
+    fn apply_size_hints(&self, id: ClientId) -> (i32, i32) {
+        let geometry = self.clients[id].get_geometry();
         let (mut width, mut height) = (
             cmp::max(geometry.width, geometry.base_width),
             cmp::max(geometry.height, geometry.base_height),
@@ -710,30 +1167,139 @@ impl<'a> Backend<'a> {
         (width, height)
     }
 
-    fn update_window_type(&mut self, index: usize) {
-        if let Some(state) = self.get_atom_prop(self.clients[index].window, self.atoms.net_wm_state)
-        {
-            if state == self.atoms.net_wm_state_fullscreen {
-                self.toggle_fullscreen(index);
+    // Adopt the states a new window was mapped with.
+    fn update_window_states(&mut self, id: ClientId) {
+        let window = self.clients[id].window;
+        for atom in self.get_atom_props(window, self.atoms.net_wm_state) {
+            if let Some(state) = self.atoms.window_state(atom) {
+                self.set_client_state(id, state, true);
             }
         }
+        // Remove states that are not supported.
+        self.set_window_states(id);
+    }
+
+    fn set_client_state(&mut self, id: ClientId, state: WindowState, enabled: bool) {
+        if self.clients[id].has_state(state) == enabled {
+            return;
+        }
+        match state {
+            WindowState::Fullscreen => self.toggle_fullscreen(id),
+            WindowState::Hidden => {
+                self.clients[id].set_state(state, enabled);
+                self.set_window_states(id);
+                let client = &self.clients[id];
+                let (monitor, workspace) = (client.monitor, client.workspace);
+                if self.monitors[monitor].get_current_workspace() == workspace {
+                    if enabled {
+                        self.unmap_client(id);
+                    } else {
+                        self.map_client(id);
+                    }
+                }
+                self.arrange(monitor, workspace);
+            }
+            WindowState::DemandsAttention => {
+                self.clients[id].set_state(state, enabled);
+                self.set_window_states(id);
+                if self.current_client != Some(id) {
+                    self.set_window_border_color(id, self.unfocused_border_color(id));
+                }
+            }
+            _ => {
+                self.clients[id].set_state(state, enabled);
+                self.set_window_states(id);
+                if state == WindowState::Above {
+                    self.restack();
+                }
+            }
+        }
+    }
+
+    // Keep shown clients that are above others at the top of the stack, and shown transients
+    // directly above their parent.
+    fn restack(&mut self) {
+        let (above, transients) = self.clients.restacking(|client| self.is_shown(client));
+        for id in above {
+            self.raise_client(id);
+        }
+        for (id, parent) in transients {
+            let mut changes: xlib::XWindowChanges = unsafe { mem::zeroed() };
+            changes.sibling = self.clients[parent].window;
+            changes.stack_mode = xlib::Above;
+            unsafe {
+                (self.xlib.XConfigureWindow)(
+                    self.display,
+                    self.clients[id].window,
+                    (xlib::CWSibling | xlib::CWStackMode) as u32,
+                    &mut changes,
+                )
+            };
+            self.clients.place_above(id, parent);
+        }
+        self.set_client_list_stacking();
+    }
+
+    // Raise a client to the top of the stack.
+    fn raise_client(&mut self, id: ClientId) {
+        unsafe { (self.xlib.XRaiseWindow)(self.display, self.clients[id].window) };
+        self.clients.raise(id);
+    }
+
+    // Transients float over their parent, on the same monitor and workspace.
+    fn update_transient(&mut self, id: ClientId) {
+        let mut parent_window = 0;
+        if unsafe {
+            (self.xlib.XGetTransientForHint)(
+                self.display,
+                self.clients[id].window,
+                &mut parent_window,
+            )
+        } == 0
+        {
+            return;
+        }
+        let client = &mut self.clients[id];
+        client.transient_for = Some(parent_window);
+        client.floating = true;
+        // The parent may be unmanaged, such as the root or an unmapped window.
+        let Some(parent) = self
+            .clients
+            .find(parent_window)
+            .and_then(|id| self.clients.get(id))
+        else {
+            return;
+        };
+        let (monitor, workspace) = (parent.monitor, parent.workspace);
+        let parent_geometry = parent.get_geometry().clone();
+        let client = &mut self.clients[id];
+        client.monitor = monitor;
+        client.workspace = workspace;
+        // Center the transient over its parent.
+        let geometry = client.get_geometry();
+        let x = parent_geometry.x + (parent_geometry.width - geometry.width) / 2;
+        let y = parent_geometry.y + (parent_geometry.height - geometry.height) / 2;
+        self.move_client(id, x, y);
+    }
+
+    fn update_window_type(&mut self, id: ClientId) {
         if let Some(window_type) =
-            self.get_atom_prop(self.clients[index].window, self.atoms.net_wm_window_type)
+            self.get_atom_prop(self.clients[id].window, self.atoms.net_wm_window_type)
         {
             let dialog = window_type == self.atoms.net_wm_window_type_dialog;
             let dock = window_type == self.atoms.net_wm_window_type_dock;
             if dialog || dock {
-                self.clients[index].floating = true;
+                self.clients[id].floating = true;
                 if dock {
-                    self.clients[index].dock = true;
+                    self.clients[id].dock = true;
                 }
             }
         }
     }
 
-    fn set_client_monitor(&mut self, index: usize) {
+    fn set_client_monitor(&mut self, id: ClientId) {
         // Ensure that the client's monitor is correct.
-        let client = &mut self.clients[index];
+        let client = &mut self.clients[id];
         client.update_geometry(self.xlib, self.display);
         let geometry = client.get_geometry();
         if let Some(monitor_index) = self
@@ -744,116 +1310,179 @@ impl<'a> Backend<'a> {
             if client.monitor != monitor_index {
                 client.monitor = monitor_index;
                 client.workspace = self.monitors[monitor_index].get_current_workspace();
+                self.set_client_desktop(id);
             }
         }
     }
 
-    // Set new input focus. If index is None, set focus to root.
-    fn set_focus(&mut self, index: Option<usize>) {
+    // Set new input focus. If id is None, set focus to root.
+    fn set_focus(&mut self, id: Option<ClientId>) {
         if let Some(current_client) = self.current_client {
-            if current_client < self.clients.len() {
+            if self.clients.contains(current_client) {
                 self.unfocus(current_client);
             }
         }
-        let new_focus = match index {
-            Some(index) => {
-                let client = &self.clients[index];
+        let new_focus = match id {
+            Some(id) => {
+                let client = &self.clients[id];
                 if client.dock {
                     // Windows of dock type should not be focusable.
                     return;
                 };
-                self.monitors[self.clients[index].monitor]
-                    .set_last_selected_client(self.clients[index].workspace, Some(index));
+                self.monitors[self.clients[id].monitor]
+                    .set_last_selected_client(self.clients[id].workspace, Some(id));
                 client.window
             }
             None => self.root,
         };
-        self.current_client = index;
+        self.current_client = id;
+        self.set_active_window(id.map_or(0, |id| self.clients[id].window));
+        let never_focus = id.is_some_and(|id| self.clients[id].never_focus);
+        if !never_focus {
+            unsafe {
+                (self.xlib.XSetInputFocus)(
+                    self.display,
+                    new_focus,
+                    xlib::RevertToPointerRoot,
+                    xlib::CurrentTime,
+                );
+            }
+        }
+        if let Some(id) = id {
+            // Clients that handle focus themselves are asked to take it.
+            self.send_xevent_atom(new_focus, self.atoms.wm_take_focus);
+            self.clear_urgency(id);
+        }
         unsafe {
-            (self.xlib.XSetInputFocus)(
+            (self.xlib.XSetWindowBorder)(
                 self.display,
                 new_focus,
-                xlib::RevertToPointerRoot,
-                xlib::CurrentTime,
+                self.settings.border_focused_color,
             );
-            (self.xlib.XSetWindowBorder)(self.display, new_focus, config::BORDER_FOCUSED_COLOR);
         }
     }
 
-    fn unfocus(&mut self, index: usize) {
-        unsafe {
-            (self.xlib.XSetWindowBorder)(
-                self.display,
-                self.clients[index].window,
-                config::BORDER_NORMAL_COLOR,
-            );
+    fn unfocus(&mut self, id: ClientId) {
+        self.set_window_border_color(id, self.unfocused_border_color(id));
+    }
+
+    // Urgent clients stand out until they are focused.
+    fn unfocused_border_color(&self, id: ClientId) -> u64 {
+        let client = &self.clients[id];
+        if client.urgent || client.has_state(WindowState::DemandsAttention) {
+            self.settings.border_urgent_color
+        } else {
+            self.settings.border_normal_color
         }
     }
 
-    fn set_border(&mut self, index: usize, width: i32) {
-        if self.clients[index].dock {
+    fn set_window_border_color(&self, id: ClientId, color: u64) {
+        unsafe { (self.xlib.XSetWindowBorder)(self.display, self.clients[id].window, color) };
+    }
+
+    // Read the input model and urgency from WM_HINTS.
+    fn update_wm_hints(&mut self, id: ClientId) {
+        let window = self.clients[id].window;
+        let hints = unsafe { (self.xlib.XGetWMHints)(self.display, window) };
+        if hints.is_null() {
+            return;
+        }
+        let wm_hints = unsafe { &*hints };
+        let client = &mut self.clients[id];
+        let urgent_hint = wm_hints.flags & xlib::XUrgencyHint != 0;
+        client.urgent = urgent_hint;
+        // A client that leaves out the input field still accepts focus.
+        client.never_focus = wm_hints.flags & xlib::InputHint != 0 && wm_hints.input == 0;
+        unsafe { (self.xlib.XFree)(hints.cast()) };
+        // The focused client already has the attention it asks for.
+        if urgent_hint && self.current_client == Some(id) {
+            self.clear_urgency(id);
+        }
+    }
+
+    fn clear_urgency(&mut self, id: ClientId) {
+        self.clients[id].urgent = false;
+        if self.clients[id].has_state(WindowState::DemandsAttention) {
+            self.set_client_state(id, WindowState::DemandsAttention, false);
+        }
+        let window = self.clients[id].window;
+        let hints = unsafe { (self.xlib.XGetWMHints)(self.display, window) };
+        if hints.is_null() {
+            return;
+        }
+        let wm_hints = unsafe { &mut *hints };
+        if wm_hints.flags & xlib::XUrgencyHint != 0 {
+            wm_hints.flags &= !xlib::XUrgencyHint;
+            unsafe { (self.xlib.XSetWMHints)(self.display, window, hints) };
+        }
+        unsafe { (self.xlib.XFree)(hints.cast()) };
+    }
+
+    fn set_border(&mut self, id: ClientId, width: i32) {
+        if self.clients[id].dock {
             // Do not set border for window's with type dock.
             return;
         }
         let mut changes: xlib::XWindowChanges = unsafe { mem::zeroed() };
         changes.border_width = width;
-        self.clients[index].get_geometry_mut().border_width = width;
+        self.clients[id].get_geometry_mut().border_width = width;
         unsafe {
             (self.xlib.XConfigureWindow)(
                 self.display,
-                self.clients[index].window,
+                self.clients[id].window,
                 xlib::CWBorderWidth as u32,
                 &mut changes,
             );
         }
     }
 
-    fn toggle_fullscreen(&mut self, index: usize) {
+    fn toggle_fullscreen(&mut self, id: ClientId) {
         // Toggle client fullscreen state.
-        self.clients[index].toggle_fullscreen();
-        if self.clients[index].fullscreen {
+        self.clients[id].toggle_fullscreen();
+        self.apply_fullscreen(id);
+    }
+
+    // Resize a client to its monitor if it is fullscreen, and otherwise back to its old geometry.
+    fn apply_fullscreen(&mut self, id: ClientId) {
+        if self.clients[id].fullscreen {
             // Make client fullscreen.
-            self.set_window_state(
-                self.clients[index].window,
-                self.atoms.net_wm_state_fullscreen,
-            );
-            self.clients[index].get_geometry_mut().border_width = 0;
+            self.set_window_states(id);
+            self.clients[id].get_geometry_mut().border_width = 0;
+            let monitor = &self.monitors[self.clients[id].monitor];
             self.move_resize_client(
-                index,
-                self.monitors[self.current_monitor].get_x(),
-                self.monitors[self.current_monitor].get_y(),
-                self.monitors[self.current_monitor].get_width(),
-                self.monitors[self.current_monitor].get_height(),
+                id,
+                monitor.get_x(),
+                monitor.get_y(),
+                monitor.get_width(),
+                monitor.get_height(),
             );
-            unsafe { (self.xlib.XRaiseWindow)(self.display, self.clients[index].window) };
+            self.raise_client(id);
+            self.set_client_list_stacking();
         } else {
             // Get client out of fullscreen.
-            self.set_window_state(self.clients[index].window, 0);
-            let client = self.clients[index].clone();
+            self.set_window_states(id);
+            let client = self.clients[id].clone();
             let old_geometry = client.get_old_geometry();
-            self.clients[index].get_geometry_mut().border_width = old_geometry.border_width;
+            self.clients[id].get_geometry_mut().border_width = old_geometry.border_width;
             // Restore old geometry.
             self.move_resize_client(
-                index,
+                id,
                 old_geometry.x,
                 old_geometry.y,
                 old_geometry.width,
                 old_geometry.height,
             );
-            self.arrange(
-                self.current_monitor,
-                self.monitors[self.current_monitor].get_current_workspace(),
-            );
+            self.arrange(self.clients[id].monitor, self.clients[id].workspace);
         }
     }
 
-    fn set_focus_and_warp(&mut self, index: usize) {
-        self.set_focus(Some(index));
-        if let Some(index) = self.current_client {
+    fn set_focus_and_warp(&mut self, id: ClientId) {
+        self.set_focus(Some(id));
+        if let Some(id) = self.current_client {
             // set_focus was successful, warp to window location.
-            let geometry = self.clients[index].get_geometry();
+            let geometry = self.clients[id].get_geometry();
             self.cursor_warp(
-                &self.clients[index].window,
+                &self.clients[id].window,
                 geometry.width / 2,
                 geometry.height / 2,
             );
@@ -861,7 +1490,7 @@ impl<'a> Backend<'a> {
     }
 
     fn cursor_warp(&self, window: &xlib::Window, x: i32, y: i32) {
-        if config::CURSOR_WARP {
+        if self.settings.cursor_warp {
             unsafe { (self.xlib.XWarpPointer)(self.display, 0, *window, 0, 0, 0, 0, x, y) };
         }
     }
@@ -871,19 +1500,6 @@ impl<'a> Backend<'a> {
         self.set_focus(self.monitors[self.current_monitor].get_last_selected_client(workspace));
     }
 
-    fn fetch_monitors(&mut self) -> CritResult<()> {
-        let mut screen_count = 0;
-        let raw_infos =
-            unsafe { (self.xinerama_xlib.XineramaQueryScreens)(self.display, &mut screen_count) };
-        let xinerama_infos: &[xinerama::XineramaScreenInfo] =
-            unsafe { slice::from_raw_parts(raw_infos, screen_count as usize) };
-        self.monitors = xinerama_infos
-            .iter()
-            .map(|info| Monitor::new(&self.layouts[0], info))
-            .collect();
-        Ok(())
-    }
-
     fn send_xevent_atom(&self, window: xlib::Window, atom: xlib::Atom) -> bool {
         let mut array: *mut xlib::Atom = unsafe { std::mem::zeroed() };
         let mut length = unsafe { std::mem::zeroed() };
@@ -1,13 +1,12 @@
 use crate::{
-    backend::client::WindowGeometry,
+    backend::client::{ClientId, WindowGeometry},
     error::{CritError, CritResult},
-    layouts::{BarStatus, Layout},
+    layouts::Layout,
 };
-use serde::Serialize;
-use std::fmt;
-use x11_dl::xinerama;
+use serde::{Serialize, Serializer};
+use std::{cmp, fmt};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MonitorGeometry {
     pub x: i32,
     pub y: i32,
@@ -37,36 +36,192 @@ impl MonitorGeometry {
     }
 }
 
+// Space reserved at each edge of a monitor, such as by bars and docks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Margins {
+    pub left: i32,
+    pub right: i32,
+    pub top: i32,
+    pub bottom: i32,
+}
+
+impl Margins {
+    pub fn new(left: i32, right: i32, top: i32, bottom: i32) -> Self {
+        Self {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    // Margins that cover both self and other.
+    pub fn max(&self, other: &Self) -> Self {
+        Self::new(
+            cmp::max(self.left, other.left),
+            cmp::max(self.right, other.right),
+            cmp::max(self.top, other.top),
+            cmp::max(self.bottom, other.bottom),
+        )
+    }
+}
+
+// Space reserved at an edge of the screen by a dock, along with the range of the edge it covers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StrutEdge {
+    pub size: i32,
+    pub start: i32,
+    pub end: i32,
+}
+
+impl StrutEdge {
+    // Size reserved on a monitor spanning from start to end along the edge, where offset is the
+    // distance from the edge of the screen to the edge of the monitor.
+    fn reserved(&self, start: i32, end: i32, offset: i32) -> i32 {
+        if self.size > 0 && self.start < end && self.end >= start {
+            cmp::max(self.size - offset, 0)
+        } else {
+            0
+        }
+    }
+}
+
+// Struts from _NET_WM_STRUT_PARTIAL or _NET_WM_STRUT. Sizes are relative to the edges of the screen
+// rather than of any monitor.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Strut {
+    pub left: StrutEdge,
+    pub right: StrutEdge,
+    pub top: StrutEdge,
+    pub bottom: StrutEdge,
+}
+
+impl Strut {
+    // Values are left, right, top and bottom sizes, followed by the start and end of the left,
+    // right, top and bottom edges.
+    pub fn from_partial(values: &[u64]) -> Option<Self> {
+        let values: &[u64; 12] = values.get(..12)?.try_into().ok()?;
+        let edge = |size: usize, start: usize| StrutEdge {
+            size: values[size] as i32,
+            start: values[start] as i32,
+            end: values[start + 1] as i32,
+        };
+        Some(Self {
+            left: edge(0, 4),
+            right: edge(1, 6),
+            top: edge(2, 8),
+            bottom: edge(3, 10),
+        })
+    }
+
+    // _NET_WM_STRUT only has sizes, so each edge covers the whole screen.
+    pub fn from_sizes(values: &[u64], screen_width: i32, screen_height: i32) -> Option<Self> {
+        let values: &[u64; 4] = values.get(..4)?.try_into().ok()?;
+        let edge = |size: usize, length: i32| StrutEdge {
+            size: values[size] as i32,
+            start: 0,
+            end: length - 1,
+        };
+        Some(Self {
+            left: edge(0, screen_height),
+            right: edge(1, screen_height),
+            top: edge(2, screen_width),
+            bottom: edge(3, screen_width),
+        })
+    }
+
+    // Space reserved on the given monitor.
+    pub fn margins(
+        &self,
+        monitor: &MonitorGeometry,
+        screen_width: i32,
+        screen_height: i32,
+    ) -> Margins {
+        let (x_end, y_end) = (monitor.x + monitor.width, monitor.y + monitor.height);
+        Margins::new(
+            self.left.reserved(monitor.y, y_end, monitor.x),
+            self.right.reserved(monitor.y, y_end, screen_width - x_end),
+            self.top.reserved(monitor.x, x_end, monitor.y),
+            self.bottom
+                .reserved(monitor.x, x_end, screen_height - y_end),
+        )
+    }
+
+    // Space reserved at the edges of the screen.
+    pub fn screen_margins(&self) -> Margins {
+        Margins::new(
+            self.left.size,
+            self.right.size,
+            self.top.size,
+            self.bottom.size,
+        )
+    }
+}
+
+// Whether the docks of a monitor are shown.
+#[derive(Debug, Default)]
+pub enum BarStatus {
+    #[default]
+    Show,
+    Hide,
+}
+
+impl Serialize for BarStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bool(match self {
+            Self::Show => true,
+            Self::Hide => false,
+        })
+    }
+}
+
+// Serde only implements Serialize for arrays of up to 32 elements.
+fn serialize_array<S, T, const N: usize>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    serializer.collect_seq(array)
+}
+
 #[derive(Serialize)]
 pub struct Monitor<const WORKSPACES: usize> {
+    // Name of the RandR output, which identifies the monitor when outputs change.
+    name: String,
     current_workspace: usize,
     geometry: MonitorGeometry,
     layout: Layout,
     bar_status: BarStatus,
-    // TODO: Serialize if possible.
-    #[serde(skip_serializing)]
-    last_selected_client: [Option<usize>; WORKSPACES],
+    // Space reserved by the struts of docks.
+    margins: Margins,
+    // Client that was focused last on each workspace.
+    #[serde(serialize_with = "serialize_array")]
+    last_selected_client: [Option<ClientId>; WORKSPACES],
 }
 
 impl<const WORKSPACES: usize> fmt::Debug for Monitor<WORKSPACES> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // Write all fields in Monitor except layout.
-        write!(f, "{:?}", (self.current_workspace, &self.geometry))
+        write!(
+            f,
+            "{:?}",
+            (&self.name, self.current_workspace, &self.geometry)
+        )
     }
 }
 
 impl<const WORKSPACES: usize> Monitor<WORKSPACES> {
-    pub fn new(layout: &Layout, info: &xinerama::XineramaScreenInfo) -> Self {
+    pub fn new(name: &str, layout: &Layout, geometry: MonitorGeometry) -> Self {
         Self {
+            name: name.to_owned(),
             current_workspace: 0,
-            geometry: MonitorGeometry::new(
-                info.x_org as i32,
-                info.y_org as i32,
-                info.width as i32,
-                info.height as i32,
-            ),
+            geometry,
             layout: layout.clone(),
             bar_status: BarStatus::default(),
+            margins: Margins::default(),
             last_selected_client: [None; WORKSPACES],
         }
     }
@@ -76,13 +231,14 @@ impl<const WORKSPACES: usize> Monitor<WORKSPACES> {
     }
 
     pub fn set_current_workspace(&mut self, workspace: usize) -> CritResult<()> {
-        if workspace <= WORKSPACES {
+        if workspace < WORKSPACES {
             self.current_workspace = workspace;
             Ok(())
         } else {
             Err(CritError::Other(format!(
                 "Workspace of value {} cannot be set. Maximum value is {}",
-                workspace, WORKSPACES
+                workspace,
+                WORKSPACES - 1
             )))
         }
     }
@@ -95,10 +251,18 @@ impl<const WORKSPACES: usize> Monitor<WORKSPACES> {
         self.layout = layout.clone();
     }
 
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
     pub fn get_geometry(&self) -> &MonitorGeometry {
         &self.geometry
     }
 
+    pub fn set_geometry(&mut self, geometry: MonitorGeometry) {
+        self.geometry = geometry;
+    }
+
     pub fn get_x(&self) -> i32 {
         self.geometry.x
     }
@@ -119,14 +283,39 @@ impl<const WORKSPACES: usize> Monitor<WORKSPACES> {
         &self.bar_status
     }
 
-    pub fn get_last_selected_client(&self, workspace: usize) -> Option<usize> {
+    pub fn is_bar_shown(&self) -> bool {
+        matches!(self.bar_status, BarStatus::Show)
+    }
+
+    pub fn get_margins(&self) -> &Margins {
+        &self.margins
+    }
+
+    pub fn set_margins(&mut self, margins: Margins) {
+        self.margins = margins;
+    }
+
+    pub fn get_last_selected_client(&self, workspace: usize) -> Option<ClientId> {
         self.last_selected_client[workspace]
     }
 
-    pub fn set_last_selected_client(&mut self, workspace: usize, client: Option<usize>) {
+    pub fn set_last_selected_client(&mut self, workspace: usize, client: Option<ClientId>) {
         self.last_selected_client[workspace] = client;
     }
 
+    // Forget a client that is no longer managed.
+    pub fn forget_client(&mut self, client: ClientId) {
+        for last_selected_client in self.last_selected_client.iter_mut() {
+            if *last_selected_client == Some(client) {
+                *last_selected_client = None;
+            }
+        }
+    }
+
+    pub fn clear_last_selected_clients(&mut self) {
+        self.last_selected_client = [None; WORKSPACES];
+    }
+
     pub fn toggle_bar_status(&mut self) {
         self.bar_status = match self.bar_status {
             BarStatus::Show => BarStatus::Hide,
@@ -134,3 +323,122 @@ impl<const WORKSPACES: usize> Monitor<WORKSPACES> {
         }
     }
 }
+
+// Match the monitors to the outputs that are connected now. Monitors are kept by name, so their
+// workspace, layout and bar keep their state while disconnected and are restored when their output
+// is connected again. Returns the new index of every previous monitor, which is None for monitors
+// that have been disconnected.
+pub fn reconfigure<const WORKSPACES: usize>(
+    monitors: &mut Vec<Monitor<WORKSPACES>>,
+    disconnected: &mut Vec<Monitor<WORKSPACES>>,
+    outputs: Vec<(String, MonitorGeometry)>,
+    layout: &Layout,
+) -> Vec<Option<usize>> {
+    let mut previous: Vec<Option<Monitor<WORKSPACES>>> = monitors.drain(..).map(Some).collect();
+    let mut indices = vec![None; previous.len()];
+    for (name, geometry) in outputs {
+        let mut monitor = if let Some(index) = previous
+            .iter()
+            .position(|monitor| monitor.as_ref().is_some_and(|m| m.name == name))
+        {
+            indices[index] = Some(monitors.len());
+            previous[index].take().unwrap()
+        } else if let Some(index) = disconnected.iter().position(|m| m.name == name) {
+            let mut monitor = disconnected.remove(index);
+            // Clients may have been moved while the monitor was disconnected.
+            monitor.clear_last_selected_clients();
+            monitor
+        } else {
+            Monitor::new(&name, layout, geometry.clone())
+        };
+        monitor.set_geometry(geometry);
+        monitors.push(monitor);
+    }
+    disconnected.extend(previous.into_iter().flatten());
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reconfigure, Margins, Monitor, MonitorGeometry, Strut};
+    use crate::config;
+
+    #[test]
+    fn strut_margins() {
+        // Two monitors side by side, the right one being shorter.
+        let (screen_width, screen_height) = (3840, 1080);
+        let left = MonitorGeometry::new(0, 0, 1920, 1080);
+        let right = MonitorGeometry::new(1920, 0, 1920, 900);
+        // A bar at the top of the left monitor.
+        let top = Strut::from_partial(&[0, 0, 24, 0, 0, 0, 0, 0, 0, 1919, 0, 0]).unwrap();
+        assert_eq!(
+            top.margins(&left, screen_width, screen_height),
+            Margins::new(0, 0, 24, 0)
+        );
+        assert_eq!(
+            top.margins(&right, screen_width, screen_height),
+            Margins::default()
+        );
+        // A bar at the bottom of the right monitor reserves from the bottom of the screen.
+        let bottom = Strut::from_partial(&[0, 0, 0, 210, 0, 0, 0, 0, 0, 0, 1920, 3839]).unwrap();
+        assert_eq!(
+            bottom.margins(&right, screen_width, screen_height),
+            Margins::new(0, 0, 0, 30)
+        );
+        assert_eq!(
+            bottom.margins(&left, screen_width, screen_height),
+            Margins::default()
+        );
+        // Without ranges, the strut spans the whole edge of the screen.
+        let full = Strut::from_sizes(&[0, 0, 24, 0], screen_width, screen_height).unwrap();
+        assert_eq!(
+            full.margins(&right, screen_width, screen_height),
+            Margins::new(0, 0, 24, 0)
+        );
+        assert!(Strut::from_partial(&[0, 0, 24, 0]).is_none());
+        assert!(Strut::from_sizes(&[0, 24], screen_width, screen_height).is_none());
+    }
+
+    #[test]
+    fn reconfigure_monitors() {
+        let layouts = config::get_layouts();
+        let output = |name: &str, x| (name.to_owned(), MonitorGeometry::new(x, 0, 1920, 1080));
+        let mut monitors: Vec<Monitor<9>> = Vec::new();
+        let mut disconnected = Vec::new();
+        let indices = reconfigure(
+            &mut monitors,
+            &mut disconnected,
+            vec![output("eDP-1", 0), output("HDMI-1", 1920)],
+            &layouts[0],
+        );
+        assert!(indices.is_empty());
+        monitors[1].set_current_workspace(4).unwrap();
+        monitors[1].set_layout(&layouts[1]);
+        monitors[1].toggle_bar_status();
+        // Undocking removes the external monitor, but keeps its state.
+        let indices = reconfigure(
+            &mut monitors,
+            &mut disconnected,
+            vec![output("eDP-1", 0)],
+            &layouts[0],
+        );
+        assert_eq!(indices, vec![Some(0), None]);
+        assert_eq!(monitors.len(), 1);
+        assert_eq!(disconnected[0].get_name(), "HDMI-1");
+        // Docking again restores it, even at another position.
+        let indices = reconfigure(
+            &mut monitors,
+            &mut disconnected,
+            vec![output("HDMI-1", 0), output("eDP-1", 1920)],
+            &layouts[0],
+        );
+        assert_eq!(indices, vec![Some(1)]);
+        assert!(disconnected.is_empty());
+        assert_eq!(monitors[0].get_name(), "HDMI-1");
+        assert_eq!(monitors[0].get_current_workspace(), 4);
+        assert_eq!(monitors[0].get_layout().name, "float");
+        assert!(!monitors[0].is_bar_shown());
+        assert_eq!(monitors[0].get_x(), 0);
+        assert_eq!(monitors[1].get_x(), 1920);
+    }
+}
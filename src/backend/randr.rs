@@ -0,0 +1,239 @@
+use crate::backend::{
+    monitor::{self, Monitor, MonitorGeometry},
+    Backend,
+};
+use std::{ffi::CStr, slice};
+use x11_dl::{xinerama, xrandr};
+
+impl Backend<'_> {
+    // Monitors are read from RandR 1.5, which names them after their outputs.
+    const RANDR_MAJOR: i32 = 1;
+    const RANDR_MINOR: i32 = 5;
+    // Name of the single monitor covering the root window, used without RandR and Xinerama.
+    const ROOT_MONITOR: &'static str = "root";
+
+    // Listen to changes of outputs if the server supports RandR monitors.
+    pub fn select_randr_input(&mut self) {
+        let Some(xrandr) = self.xrandr else {
+            info!("RandR is not available");
+            return;
+        };
+        let (mut event_base, mut error_base) = (0, 0);
+        let (mut major, mut minor) = (0, 0);
+        let supported = unsafe {
+            (xrandr.XRRQueryExtension)(self.display, &mut event_base, &mut error_base) != 0
+                && (xrandr.XRRQueryVersion)(self.display, &mut major, &mut minor) != 0
+        };
+        if !supported || (major, minor) < (Self::RANDR_MAJOR, Self::RANDR_MINOR) {
+            info!(
+                "RandR {}.{} is not supported by the server",
+                Self::RANDR_MAJOR,
+                Self::RANDR_MINOR
+            );
+            return;
+        }
+        unsafe {
+            (xrandr.XRRSelectInput)(
+                self.display,
+                self.root,
+                xrandr::RRScreenChangeNotifyMask
+                    | xrandr::RRCrtcChangeNotifyMask
+                    | xrandr::RROutputChangeNotifyMask,
+            )
+        };
+        self.randr_event_base = Some(event_base);
+    }
+
+    pub fn is_randr_event(&self, event_type: i32) -> bool {
+        self.randr_event_base.is_some_and(|base| {
+            event_type == base + xrandr::RRScreenChangeNotify
+                || event_type == base + xrandr::RRNotify
+        })
+    }
+
+    pub fn fetch_monitors(&mut self) {
+        self.monitors = self
+            .query_monitors()
+            .into_iter()
+            .map(|(name, geometry)| Monitor::new(&name, &self.settings.layouts[0], geometry))
+            .collect();
+        info!("Found monitors {:?}", self.monitors);
+    }
+
+    // Apply changes to the connected outputs. Clients of a disconnected monitor are moved to the
+    // focused monitor, and back to their own monitor once it is connected again.
+    pub fn update_monitors(&mut self) {
+        let outputs = self.query_monitors();
+        let unchanged = outputs.len() == self.monitors.len()
+            && outputs
+                .iter()
+                .zip(&self.monitors)
+                .all(|((name, geometry), monitor)| {
+                    name == monitor.get_name() && geometry == monitor.get_geometry()
+                });
+        if unchanged {
+            return;
+        }
+        info!(
+            "Monitors changed to {:?}",
+            outputs.iter().map(|(name, _)| name).collect::<Vec<_>>()
+        );
+        let previous: Vec<(String, MonitorGeometry)> = self
+            .monitors
+            .iter()
+            .map(|monitor| {
+                (
+                    monitor.get_name().to_owned(),
+                    monitor.get_geometry().clone(),
+                )
+            })
+            .collect();
+        let indices = monitor::reconfigure(
+            &mut self.monitors,
+            &mut self.disconnected_monitors,
+            outputs,
+            &self.settings.layouts[0],
+        );
+        let fallback = indices[self.current_monitor].unwrap_or(0);
+        self.current_monitor = fallback;
+        for id in self.clients.ids() {
+            if self.clients[id].dock {
+                // Docks belong to the monitor they are placed on.
+                self.set_client_monitor(id);
+                continue;
+            }
+            let previous_monitor = self.clients[id].monitor;
+            let (previous_name, previous_geometry) = &previous[previous_monitor];
+            let returning = self.clients[id]
+                .origin_monitor
+                .as_ref()
+                .and_then(|name| self.find_monitor(name));
+            let client = &mut self.clients[id];
+            client.monitor = match (returning, indices[previous_monitor]) {
+                (Some(monitor), _) => {
+                    client.origin_monitor = None;
+                    monitor
+                }
+                (None, Some(monitor)) => monitor,
+                (None, None) => {
+                    // Clients that have already been moved keep the monitor they came from.
+                    client
+                        .origin_monitor
+                        .get_or_insert_with(|| previous_name.clone());
+                    fallback
+                }
+            };
+            let geometry = self.monitors[client.monitor].get_geometry().clone();
+            if client.fullscreen {
+                self.move_resize_client(
+                    id,
+                    geometry.x,
+                    geometry.y,
+                    geometry.width,
+                    geometry.height,
+                );
+            } else if client.floating {
+                // Keep the position of the client relative to its monitor.
+                let x = client.get_geometry().x - previous_geometry.x + geometry.x;
+                let y = client.get_geometry().y - previous_geometry.y + geometry.y;
+                self.move_client(id, x, y);
+            }
+        }
+        self.update_struts();
+        self.arrange_all();
+        self.map_shown_clients();
+        self.set_current_desktop();
+        if self
+            .current_client
+            .is_some_and(|id| self.clients[id].monitor != self.current_monitor)
+        {
+            self.focus_current_monitor();
+        }
+    }
+
+    pub fn find_monitor(&self, name: &str) -> Option<usize> {
+        self.monitors
+            .iter()
+            .position(|monitor| monitor.get_name() == name)
+    }
+
+    // Monitors with their names and geometries, read from RandR if it is supported and otherwise
+    // from Xinerama, which does not name its screens, so they are named by index. Without either,
+    // the root window is a single monitor.
+    fn query_monitors(&self) -> Vec<(String, MonitorGeometry)> {
+        let monitors = match (
+            self.xrandr.filter(|_| self.randr_event_base.is_some()),
+            self.xinerama_xlib,
+        ) {
+            (Some(xrandr), _) => self.query_randr_monitors(xrandr),
+            (None, Some(xinerama_xlib)) => self.query_xinerama_monitors(xinerama_xlib),
+            (None, None) => Vec::new(),
+        };
+        if monitors.is_empty() {
+            let (width, height) = self.screen_size();
+            return vec![(
+                Self::ROOT_MONITOR.to_owned(),
+                MonitorGeometry::new(0, 0, width, height),
+            )];
+        }
+        monitors
+    }
+
+    fn query_randr_monitors(&self, xrandr: &xrandr::Xrandr) -> Vec<(String, MonitorGeometry)> {
+        let mut count = 0;
+        let infos = unsafe { (xrandr.XRRGetMonitors)(self.display, self.root, 1, &mut count) };
+        if infos.is_null() {
+            return Vec::new();
+        }
+        let monitors = unsafe { slice::from_raw_parts(infos, count as usize) }
+            .iter()
+            .map(|info| {
+                let name = unsafe { (self.xlib.XGetAtomName)(self.display, info.name) };
+                let name_string = if name.is_null() {
+                    String::new()
+                } else {
+                    let string = unsafe { CStr::from_ptr(name) }
+                        .to_string_lossy()
+                        .into_owned();
+                    unsafe { (self.xlib.XFree)(name.cast()) };
+                    string
+                };
+                (
+                    name_string,
+                    MonitorGeometry::new(info.x, info.y, info.width, info.height),
+                )
+            })
+            .collect();
+        unsafe { (xrandr.XRRFreeMonitors)(infos) };
+        monitors
+    }
+
+    fn query_xinerama_monitors(
+        &self,
+        xinerama_xlib: &xinerama::Xlib,
+    ) -> Vec<(String, MonitorGeometry)> {
+        let mut count = 0;
+        let infos = unsafe { (xinerama_xlib.XineramaQueryScreens)(self.display, &mut count) };
+        if infos.is_null() {
+            return Vec::new();
+        }
+        let xinerama_infos: &[xinerama::XineramaScreenInfo] =
+            unsafe { slice::from_raw_parts(infos, count as usize) };
+        let monitors = xinerama_infos
+            .iter()
+            .map(|info| {
+                (
+                    info.screen_number.to_string(),
+                    MonitorGeometry::new(
+                        info.x_org as i32,
+                        info.y_org as i32,
+                        info.width as i32,
+                        info.height as i32,
+                    ),
+                )
+            })
+            .collect();
+        unsafe { (self.xlib.XFree)(infos.cast()) };
+        monitors
+    }
+}
@@ -0,0 +1,393 @@
+use crate::{
+    backend::{
+        client::{Client, ClientId, Clients, WindowGeometry},
+        monitor::Monitor,
+        Backend,
+    },
+    error::{CritError, CritResult},
+    layouts::Layout,
+};
+use serde::{Deserialize, Serialize};
+use std::{mem, ptr};
+use x11_dl::xlib;
+
+// State of a client that is kept when critwm restarts in place. Clients are found again by their
+// window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientSession {
+    pub window: xlib::Window,
+    // Monitors are found by name, as outputs may be connected in another order.
+    pub monitor: String,
+    pub workspace: usize,
+    pub floating: bool,
+    pub fullscreen: bool,
+    // Geometry a fullscreen client returns to once it leaves fullscreen.
+    pub old_geometry: Option<WindowGeometry>,
+}
+
+impl ClientSession {
+    pub fn restore<const WORKSPACES: usize>(
+        &self,
+        client: &mut Client,
+        monitors: &[Monitor<WORKSPACES>],
+        workspaces: usize,
+    ) {
+        if let Some(monitor) = monitors
+            .iter()
+            .position(|monitor| monitor.get_name() == self.monitor)
+        {
+            client.monitor = monitor;
+        }
+        if self.workspace < workspaces {
+            client.workspace = self.workspace;
+        }
+        client.floating = self.floating;
+        if client.fullscreen != self.fullscreen {
+            client.toggle_fullscreen();
+        }
+        if let Some(old_geometry) = &self.old_geometry {
+            client.set_old_geometry(old_geometry.clone());
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorSession {
+    pub name: String,
+    pub workspace: usize,
+    // Layouts are found by name, as the new configuration may reorder them.
+    pub layout: String,
+    pub bar_shown: bool,
+}
+
+impl MonitorSession {
+    pub fn restore<const WORKSPACES: usize>(
+        &self,
+        monitor: &mut Monitor<WORKSPACES>,
+        layouts: &[Layout],
+    ) {
+        if let Err(e) = monitor.set_current_workspace(self.workspace) {
+            error!("Failed to restore workspace: {}", e);
+        }
+        if let Some(layout) = layouts.iter().find(|layout| layout.name == self.layout) {
+            monitor.set_layout(layout);
+        }
+        if monitor.is_bar_shown() != self.bar_shown {
+            monitor.toggle_bar_status();
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub clients: Vec<ClientSession>,
+    pub monitors: Vec<MonitorSession>,
+    pub current_monitor: usize,
+    pub focused: Option<xlib::Window>,
+}
+
+impl Session {
+    pub fn get_client(&self, window: xlib::Window) -> Option<&ClientSession> {
+        self.clients.iter().find(|client| client.window == window)
+    }
+
+    pub fn get_monitor(&self, name: &str) -> Option<&MonitorSession> {
+        self.monitors.iter().find(|monitor| monitor.name == name)
+    }
+
+    // Index of the monitor that was current, if it is still connected.
+    pub fn current_monitor<const WORKSPACES: usize>(
+        &self,
+        monitors: &[Monitor<WORKSPACES>],
+    ) -> Option<usize> {
+        let saved = self.monitors.get(self.current_monitor)?;
+        monitors
+            .iter()
+            .position(|monitor| monitor.get_name() == saved.name)
+    }
+
+    // The client that was focused, if it is shown on the given workspace of a monitor.
+    pub fn focused_client(
+        &self,
+        clients: &Clients,
+        monitor: usize,
+        workspace: usize,
+    ) -> Option<ClientId> {
+        clients
+            .find(self.focused?)
+            .filter(|id| clients[*id].is_focusable_on(monitor, workspace))
+    }
+}
+
+impl From<&Backend<'_>> for Session {
+    fn from(backend: &Backend<'_>) -> Self {
+        Self {
+            clients: backend
+                .clients
+                .iter()
+                .filter(|client| !client.dock)
+                .map(|client| ClientSession {
+                    window: client.window,
+                    monitor: backend.monitors[client.monitor].get_name().to_owned(),
+                    workspace: client.workspace,
+                    floating: client.floating,
+                    fullscreen: client.fullscreen,
+                    old_geometry: client.fullscreen.then(|| client.get_old_geometry().clone()),
+                })
+                .collect(),
+            monitors: backend
+                .monitors
+                .iter()
+                .map(|monitor| MonitorSession {
+                    name: monitor.get_name().to_owned(),
+                    workspace: monitor.get_current_workspace(),
+                    layout: monitor.get_layout().name.clone(),
+                    bar_shown: monitor.is_bar_shown(),
+                })
+                .collect(),
+            current_monitor: backend.current_monitor,
+            focused: backend.current_client.map(|id| backend.clients[id].window),
+        }
+    }
+}
+
+impl Backend<'_> {
+    // Save the session for the next instance, which is started once the display is closed. It is
+    // kept in a property of the root window, so that it belongs to this display.
+    pub fn restart(&mut self) -> CritResult<()> {
+        let json = serde_json::to_string(&Session::from(&*self))?;
+        self.set_prop_string(self.root, self.atoms.critwm_session, &json);
+        self.restarting = true;
+        Ok(())
+    }
+
+    pub fn is_restarting(&self) -> bool {
+        self.restarting
+    }
+
+    // Read the session saved by the previous instance and remove it, so that it is only restored
+    // once. A session is only restored if this instance was started by a restart, as one left by
+    // an instance that failed to restart may refer to windows of another session.
+    pub fn take_session(&self, restarted: bool) -> Option<Session> {
+        let json = self.get_text_prop(self.root, self.atoms.critwm_session)?;
+        unsafe { (self.xlib.XDeleteProperty)(self.display, self.root, self.atoms.critwm_session) };
+        if !restarted {
+            info!("Discarding session of a previous instance");
+            return None;
+        }
+        let mut session: Session = serde_json::from_str(&json)
+            .map_err(|e| error!("Failed to read session: {}", e))
+            .ok()?;
+        // Windows may have been destroyed while critwm restarted.
+        session.clients.retain(|client| {
+            let mut attrs: xlib::XWindowAttributes = unsafe { mem::zeroed() };
+            let status = unsafe {
+                (self.xlib.XGetWindowAttributes)(self.display, client.window, &mut attrs)
+            };
+            status != 0
+        });
+        Some(session)
+    }
+
+    // Monitors that are no longer connected are not restored.
+    pub fn restore_monitors(&mut self, session: &Session) {
+        for monitor in self.monitors.iter_mut() {
+            if let Some(saved) = session.get_monitor(monitor.get_name()) {
+                saved.restore(monitor, &self.settings.layouts);
+            }
+        }
+        if let Some(current_monitor) = session.current_monitor(&self.monitors) {
+            self.current_monitor = current_monitor;
+        }
+    }
+
+    pub fn restore_client(&mut self, id: ClientId, saved: &ClientSession) {
+        // Fullscreen is read from _NET_WM_STATE before the client is moved to its monitor.
+        let fullscreen = self.clients[id].fullscreen;
+        saved.restore(
+            &mut self.clients[id],
+            &self.monitors,
+            self.settings.workspaces.len(),
+        );
+        self.set_client_desktop(id);
+        if fullscreen || self.clients[id].fullscreen {
+            self.apply_fullscreen(id);
+        }
+    }
+
+    // Windows of the clients, which are mapped again if the new instance could not be started.
+    pub fn client_windows(&self) -> Vec<xlib::Window> {
+        self.clients.iter().map(|client| client.window).collect()
+    }
+}
+
+// Map windows on a new connection to the display, used when the clients left unmapped for a new
+// instance would otherwise stay hidden.
+pub fn map_windows(xlib: &xlib::Xlib, windows: &[xlib::Window]) -> CritResult<()> {
+    let display = unsafe { (xlib.XOpenDisplay)(ptr::null()) };
+    if display.is_null() {
+        return Err(CritError::Other("Display is null.".to_owned()));
+    }
+    for window in windows {
+        unsafe { (xlib.XMapWindow)(display, *window) };
+    }
+    // Closing the display flushes the requests.
+    unsafe { (xlib.XCloseDisplay)(display) };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientSession, MonitorSession, Session};
+    use crate::{
+        backend::{
+            client::{Client, Clients, WindowGeometry},
+            monitor::{Monitor, MonitorGeometry},
+        },
+        config,
+        layouts::{float::float, tile::tile, Layout},
+    };
+
+    fn client_session(window: u64, monitor: &str, workspace: usize) -> ClientSession {
+        ClientSession {
+            window,
+            monitor: monitor.to_owned(),
+            workspace,
+            floating: false,
+            fullscreen: false,
+            old_geometry: None,
+        }
+    }
+
+    fn monitors(layout: &Layout) -> Vec<Monitor<{ config::WORKSPACE_COUNT }>> {
+        vec![
+            Monitor::new("DP-1", layout, MonitorGeometry::new(0, 0, 1920, 1080)),
+            Monitor::new("HDMI-1", layout, MonitorGeometry::new(1920, 0, 1920, 1080)),
+        ]
+    }
+
+    #[test]
+    fn restore_session() {
+        let session = Session {
+            clients: vec![ClientSession {
+                floating: true,
+                fullscreen: true,
+                old_geometry: Some(WindowGeometry::new(10, 20, 800, 600, 1)),
+                ..client_session(0x1400003, "HDMI-1", 4)
+            }],
+            monitors: vec![MonitorSession {
+                name: "HDMI-1".to_owned(),
+                workspace: 4,
+                layout: "float".to_owned(),
+                bar_shown: false,
+            }],
+            current_monitor: 0,
+            focused: Some(0x1400003),
+        };
+        let json = serde_json::to_string(&session).unwrap();
+        let restored: Session = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, session);
+        assert_eq!(restored.get_client(0x1400003).unwrap().workspace, 4);
+        assert!(restored.get_client(0x1400004).is_none());
+    }
+
+    #[test]
+    fn restore_monitors() {
+        let layouts = [
+            Layout::new("tile", "[]=", tile),
+            Layout::new("float", "><>", float),
+        ];
+        let mut monitors = monitors(&layouts[0]);
+        let session = Session {
+            clients: Vec::new(),
+            monitors: vec![
+                MonitorSession {
+                    name: "DP-2".to_owned(),
+                    workspace: 1,
+                    layout: "float".to_owned(),
+                    bar_shown: false,
+                },
+                MonitorSession {
+                    name: "HDMI-1".to_owned(),
+                    workspace: 3,
+                    layout: "float".to_owned(),
+                    bar_shown: false,
+                },
+            ],
+            current_monitor: 1,
+            focused: None,
+        };
+        for monitor in monitors.iter_mut() {
+            if let Some(saved) = session.get_monitor(monitor.get_name()) {
+                saved.restore(monitor, &layouts);
+            }
+        }
+        // The monitor that was not saved is left as it is.
+        assert_eq!(monitors[0].get_current_workspace(), 0);
+        assert_eq!(monitors[0].get_layout().name, "tile");
+        assert!(monitors[0].is_bar_shown());
+        assert_eq!(monitors[1].get_current_workspace(), 3);
+        assert_eq!(monitors[1].get_layout().name, "float");
+        assert!(!monitors[1].is_bar_shown());
+        // The current monitor is found by name, not by its index in the previous instance.
+        assert_eq!(session.current_monitor(&monitors), Some(1));
+        let session = Session {
+            current_monitor: 0,
+            ..session
+        };
+        assert_eq!(session.current_monitor(&monitors), None);
+    }
+
+    #[test]
+    fn restore_clients() {
+        let monitors = monitors(&Layout::new("tile", "[]=", tile));
+        let workspaces = config::WORKSPACE_COUNT;
+        let mut clients = Clients::default();
+        let fullscreen_geometry = WindowGeometry::new(1920, 0, 1920, 1080, 0);
+        let old_geometry = WindowGeometry::new(2000, 100, 800, 600, 1);
+        let saved = [
+            ClientSession {
+                fullscreen: true,
+                old_geometry: Some(old_geometry.clone()),
+                ..client_session(1, "HDMI-1", 2)
+            },
+            ClientSession {
+                floating: true,
+                ..client_session(2, "DP-2", 1)
+            },
+            client_session(3, "HDMI-1", workspaces),
+        ];
+        let ids: Vec<_> = saved
+            .iter()
+            .map(|saved| {
+                // Fullscreen clients are found fullscreen on the first monitor.
+                let mut client = Client::new(fullscreen_geometry.clone(), 0, 0);
+                client.window = saved.window;
+                client.toggle_fullscreen();
+                saved.restore(&mut client, &monitors, workspaces);
+                clients.insert(client)
+            })
+            .collect();
+        let fullscreen = &clients[ids[0]];
+        assert_eq!((fullscreen.monitor, fullscreen.workspace), (1, 2));
+        assert!(fullscreen.fullscreen && !fullscreen.floating);
+        assert_eq!(fullscreen.get_old_geometry(), &old_geometry);
+        // A monitor that is no longer connected is not restored.
+        let floating = &clients[ids[1]];
+        assert_eq!((floating.monitor, floating.workspace), (0, 1));
+        assert!(floating.floating && !floating.fullscreen);
+        assert_eq!(floating.get_old_geometry(), &fullscreen_geometry);
+        // Neither is a workspace that no longer exists.
+        assert_eq!((clients[ids[2]].monitor, clients[ids[2]].workspace), (1, 0));
+        // The focused client is only focused again if it is on the current workspace.
+        let session = Session {
+            clients: saved.to_vec(),
+            monitors: Vec::new(),
+            current_monitor: 0,
+            focused: Some(1),
+        };
+        assert_eq!(session.focused_client(&clients, 1, 2), Some(ids[0]));
+        assert_eq!(session.focused_client(&clients, 1, 0), None);
+        assert_eq!(session.focused_client(&clients, 0, 2), None);
+    }
+}
@@ -1,9 +1,24 @@
-use crate::{backend::Backend, config, error::CritResult};
+use crate::{
+    backend::{
+        client::{ClientId, WindowState},
+        Backend,
+    },
+    config,
+    error::{CritError, CritResult},
+    layouts::Layout,
+    settings::Settings,
+};
+use clap::ValueEnum;
 use lazy_static::lazy_static;
-use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tokio::sync::{
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
 use x11_dl::xlib;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, ValueEnum)]
 pub enum Dir {
     Up,
     Down,
@@ -18,13 +33,16 @@ impl Dir {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Signal {
     Quit,
     KillClient,
     ToggleFloating,
     ToggleBar,
     SetLayout(usize),
+    // Set the layout with the given name or symbol, which is looked up in the layouts of the
+    // running instance.
+    SetLayoutByName(String),
     ChangeWorkspace(usize),
     MoveToWorkspace(usize),
     FocusMon(Dir),
@@ -32,40 +50,112 @@ pub enum Signal {
     // A combination of FocusStack and FocusMon. Only focuses another monitor if there is no other
     // client to focus on the stack without looping.
     FocusDir(Dir),
+    // Reload settings from the configuration file.
+    Reload,
+    // Replace critwm with a new instance of its binary, keeping every client where it is.
+    Restart,
+}
+
+impl Signal {
+    // Check that the signal refers to a workspace and layout that exist.
+    pub fn validate(&self, layouts: &[Layout]) -> CritResult<()> {
+        match *self {
+            Self::ChangeWorkspace(workspace) | Self::MoveToWorkspace(workspace)
+                if workspace >= config::WORKSPACE_COUNT =>
+            {
+                Err(CritError::Other(format!(
+                    "Workspace {} does not exist. Maximum value is {}",
+                    workspace,
+                    config::WORKSPACE_COUNT - 1
+                )))
+            }
+            Self::SetLayout(layout_index) if layout_index >= layouts.len() => Err(
+                CritError::Other(format!("Layout {} does not exist", layout_index)),
+            ),
+            Self::SetLayoutByName(ref layout) => find_layout(layouts, layout).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Outcome of handling a signal, sent back to whoever issued it.
+pub type SignalReply = oneshot::Sender<Result<(), String>>;
+
+#[derive(Debug)]
+pub struct SignalRequest {
+    pub signal: Signal,
+    pub reply: Option<SignalReply>,
 }
 
 impl Backend<'_> {
+    // Handle all queued signals in the order they were issued.
     // Returns true if quit signal is handled.
-    pub fn handle_signal(&mut self) -> CritResult<bool> {
-        if let Some(signal) = SIGNAL_STACK.lock().unwrap().pop() {
-            info!("Received signal: {:?}", signal);
-            match signal {
-                Signal::Quit => {
-                    self.quit();
-                    return Ok(true);
-                }
-                Signal::KillClient => self.kill_client(),
-                Signal::ToggleFloating => {
-                    if let Some(current_client) = self.current_client {
-                        self.toggle_floating(current_client);
-                    }
+    pub fn handle_signals(&mut self, receiver: &mut UnboundedReceiver<SignalRequest>) -> bool {
+        while let Ok(request) = receiver.try_recv() {
+            if self.handle_signal(request) {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Returns true if quit signal is handled, or if restart signal has saved the session.
+    pub fn handle_signal(&mut self, request: SignalRequest) -> bool {
+        let SignalRequest { signal, reply } = request;
+        info!("Received signal: {:?}", signal);
+        let quit = matches!(signal, Signal::Quit | Signal::Restart);
+        let result = signal
+            .validate(&self.settings.layouts)
+            .and_then(|_| self.run_signal(signal));
+        // If the session cannot be saved, the current instance keeps running.
+        let quit = quit && result.is_ok();
+        if let Err(e) = &result {
+            error!("Failed to handle signal: {}", e);
+        }
+        if let Some(reply) = reply {
+            // The issuer may have stopped waiting for the reply.
+            reply.send(result.map_err(|e| e.to_string())).ok();
+        }
+        if quit {
+            self.quit();
+        }
+        quit
+    }
+
+    fn run_signal(&mut self, signal: Signal) -> CritResult<()> {
+        match signal {
+            // Quitting is done after replying as the display is closed.
+            Signal::Quit => {}
+            Signal::KillClient => self.kill_client(),
+            Signal::ToggleFloating => {
+                if let Some(current_client) = self.current_client {
+                    self.toggle_floating(current_client);
                 }
-                Signal::ToggleBar => self.toggle_bar(),
-                Signal::SetLayout(layout_index) => self.set_layout(layout_index),
-                Signal::ChangeWorkspace(new_workspace) => self.change_workspace(new_workspace)?,
-                Signal::MoveToWorkspace(new_workspace) => self.move_to_workspace(new_workspace),
-                Signal::FocusMon(direction) => self.focus_monitor(direction),
-                Signal::FocusStack(direction) => self.focus_stack(direction),
-                Signal::FocusDir(direction) => self.focus_dir(direction),
             }
+            Signal::ToggleBar => self.toggle_bar(),
+            Signal::SetLayout(layout_index) => self.set_layout(layout_index),
+            Signal::SetLayoutByName(layout) => {
+                self.set_layout(find_layout(&self.settings.layouts, &layout)?)
+            }
+            Signal::ChangeWorkspace(new_workspace) => self.change_workspace(new_workspace)?,
+            Signal::MoveToWorkspace(new_workspace) => self.move_to_workspace(new_workspace),
+            Signal::FocusMon(direction) => self.focus_monitor(direction),
+            Signal::FocusStack(direction) => self.focus_stack(direction),
+            Signal::FocusDir(direction) => self.focus_dir(direction),
+            Signal::Reload => self.reload()?,
+            Signal::Restart => self.restart()?,
         }
-        Ok(false)
+        Ok(())
     }
 
     pub fn quit(&mut self) {
-        self.clients.iter().for_each(|client| unsafe {
-            (self.xlib.XMapWindow)(self.display, client.window);
-        });
+        self.clear_client_lists();
+        if !self.restarting {
+            // Clients on hidden workspaces are left iconic for the new instance to adopt.
+            self.clients.iter().for_each(|client| unsafe {
+                (self.xlib.XMapWindow)(self.display, client.window);
+            });
+        }
         unsafe {
             (self.xlib.XSetInputFocus)(
                 self.display,
@@ -79,47 +169,97 @@ impl Backend<'_> {
         }
     }
 
+    pub fn reload(&mut self) -> CritResult<()> {
+        let path = self
+            .settings_path
+            .as_ref()
+            .ok_or_else(|| CritError::Other("No configuration file to reload from".to_owned()))?;
+        // If the new settings are invalid, the error is returned and the current settings stay.
+        self.settings = Settings::load(self.xlib, path)?;
+        info!("Reloaded configuration file {:?}", path);
+        self.grab_keys();
+        self.grab_buttons();
+        self.set_desktop_names();
+        // Keep the layout of each monitor, picking up changes to layouts with the same name.
+        for monitor in self.monitors.iter_mut() {
+            let name = &monitor.get_layout().name;
+            if let Some(layout) = self.settings.layouts.iter().find(|l| &l.name == name) {
+                monitor.set_layout(layout);
+            }
+        }
+        // Apply border widths and colors to every client.
+        for id in self.clients.ids() {
+            if !self.clients[id].fullscreen {
+                self.set_border(id, self.settings.border);
+            }
+            let color = if self.current_client == Some(id) {
+                self.settings.border_focused_color
+            } else {
+                self.unfocused_border_color(id)
+            };
+            unsafe { (self.xlib.XSetWindowBorder)(self.display, self.clients[id].window, color) };
+        }
+        self.arrange_all();
+        Ok(())
+    }
+
     pub fn kill_client(&self) {
         if let Some(current_client) = self.current_client {
-            if let Some(client) = self.clients.get(current_client) {
-                if !client.dock {
-                    // Try kill the client nicely.
-                    if !self.send_xevent_atom(client.window, self.atoms.wm_delete) {
-                        // Force kill the client.
-                        unsafe {
-                            (self.xlib.XGrabServer)(self.display);
-                            (self.xlib.XSetErrorHandler)(Some(Self::xerror_dummy));
-                            (self.xlib.XSetCloseDownMode)(self.display, xlib::DestroyAll);
-                            (self.xlib.XKillClient)(self.display, client.window);
-                            (self.xlib.XSync)(self.display, xlib::False);
-                            (self.xlib.XSetErrorHandler)(Some(Self::xerror));
-                            (self.xlib.XUngrabServer)(self.display);
-                        }
+            self.close_client(current_client);
+        }
+    }
+
+    pub fn close_client(&self, id: ClientId) {
+        if let Some(client) = self.clients.get(id) {
+            if !client.dock {
+                // Try kill the client nicely.
+                if !self.send_xevent_atom(client.window, self.atoms.wm_delete) {
+                    // Force kill the client.
+                    unsafe {
+                        (self.xlib.XGrabServer)(self.display);
+                        (self.xlib.XSetErrorHandler)(Some(Self::xerror_dummy));
+                        (self.xlib.XSetCloseDownMode)(self.display, xlib::DestroyAll);
+                        (self.xlib.XKillClient)(self.display, client.window);
+                        (self.xlib.XSync)(self.display, xlib::False);
+                        (self.xlib.XSetErrorHandler)(Some(Self::xerror));
+                        (self.xlib.XUngrabServer)(self.display);
                     }
                 }
             }
         }
     }
 
-    pub fn toggle_floating(&mut self, index: usize) {
-        self.clients[index].floating = !self.clients[index].floating;
-        unsafe { (self.xlib.XRaiseWindow)(self.display, self.clients[index].window) };
+    pub fn toggle_floating(&mut self, id: ClientId) {
+        self.clients[id].floating = !self.clients[id].floating;
+        self.raise_client(id);
         self.arrange(
             self.current_monitor,
             self.monitors[self.current_monitor].get_current_workspace(),
         );
     }
 
+    // Show or hide the docks of the current monitor.
     pub fn toggle_bar(&mut self) {
         let monitor = &mut self.monitors[self.current_monitor];
         monitor.toggle_bar_status();
-        for workspace in 0..config::WORKSPACE_COUNT {
-            self.arrange(self.current_monitor, workspace);
+        let shown = monitor.is_bar_shown();
+        for id in self.clients.ids() {
+            let client = &self.clients[id];
+            if !client.dock || client.monitor != self.current_monitor {
+                continue;
+            }
+            if shown {
+                self.map_client(id);
+            } else {
+                self.unmap_client(id);
+            }
         }
+        self.update_struts();
+        self.arrange_all();
     }
 
     pub fn set_layout(&mut self, layout_index: usize) {
-        self.monitors[self.current_monitor].set_layout(&self.layouts[layout_index]);
+        self.monitors[self.current_monitor].set_layout(&self.settings.layouts[layout_index]);
         // Ensure that all clients in current monitor are not floating.
         for client in self.clients.iter_mut() {
             if client.monitor == self.current_monitor {
@@ -137,21 +277,21 @@ impl Backend<'_> {
         let monitor = &self.monitors[self.current_monitor];
         if monitor.get_current_workspace() != new_workspace {
             // Unmap windows that are in the old workspace.
-            self.clients
-                .iter()
-                .filter(|client| self.is_visible(monitor.get_current_workspace(), client))
-                .for_each(|client| {
-                    unsafe { (self.xlib.XUnmapWindow)(self.display, client.window) };
-                });
+            let old_workspace = monitor.get_current_workspace();
+            for id in self.clients.ids() {
+                if self.is_visible(old_workspace, &self.clients[id]) {
+                    self.unmap_client(id);
+                }
+            }
             // Map windows that are in the new workspace.
-            self.clients
-                .iter()
-                .filter(|client| self.is_visible(new_workspace, client))
-                .for_each(|client| {
-                    unsafe { (self.xlib.XMapWindow)(self.display, client.window) };
-                });
+            for id in self.clients.ids() {
+                if self.is_visible(new_workspace, &self.clients[id]) {
+                    self.map_client(id);
+                }
+            }
             // Update workspace value to new value.
             self.monitors[self.current_monitor].set_current_workspace(new_workspace)?;
+            self.set_current_desktop();
             self.focus_current_monitor();
         }
         Ok(())
@@ -160,19 +300,39 @@ impl Backend<'_> {
     pub fn move_to_workspace(&mut self, new_workspace: usize) {
         // Move currently focused client to given workspace.
         if let Some(current_client) = self.current_client {
-            if self.clients[current_client].workspace != new_workspace {
-                self.clients[current_client].workspace = new_workspace;
-                unsafe {
-                    (self.xlib.XUnmapWindow)(self.display, self.clients[current_client].window)
-                };
-                // Arrange new workspace.
-                self.arrange(self.current_monitor, new_workspace);
-                // Arrange current workspace.
-                self.arrange(
-                    self.current_monitor,
-                    self.monitors[self.current_monitor].get_current_workspace(),
-                );
+            self.move_client_to_workspace(current_client, new_workspace);
+        }
+    }
+
+    pub fn move_client_to_workspace(&mut self, id: ClientId, new_workspace: usize) {
+        let monitor = self.clients[id].monitor;
+        let old_workspace = self.clients[id].workspace;
+        if old_workspace != new_workspace {
+            self.clients[id].workspace = new_workspace;
+            self.set_client_desktop(id);
+            // The client is only mapped if its new workspace is shown on its monitor.
+            if self.monitors[monitor].get_current_workspace() == new_workspace
+                && !self.clients[id].has_state(WindowState::Hidden)
+            {
+                self.map_client(id);
+            } else {
+                self.unmap_client(id);
+            }
+            // Transients follow their parent.
+            let window = self.clients[id].window;
+            let transients: Vec<ClientId> = self
+                .clients
+                .iter()
+                .filter(|client| client.transient_for == Some(window))
+                .map(|client| client.id)
+                .collect();
+            for transient in transients {
+                self.move_client_to_workspace(transient, new_workspace);
             }
+            // Arrange new workspace.
+            self.arrange(monitor, new_workspace);
+            // Arrange old workspace.
+            self.arrange(monitor, old_workspace);
         }
     }
 
@@ -193,6 +353,7 @@ impl Backend<'_> {
                 }
             }
         };
+        self.set_current_desktop();
         self.focus_current_monitor();
         if let Some(current_client) = self.current_client {
             let geometry = self.clients[current_client].get_geometry();
@@ -214,24 +375,26 @@ impl Backend<'_> {
     pub fn focus_stack(&mut self, direction: Dir) {
         if let Some(current_client) = self.current_client {
             let workspace = self.monitors[self.current_monitor].get_current_workspace();
-            if let Some((index, _)) = match direction {
-                Dir::Up => self
-                    .clients
+            let ids = self.clients.ids();
+            let Some(position) = ids.iter().position(|id| *id == current_client) else {
+                return;
+            };
+            if let Some(id) = match direction {
+                Dir::Up => ids
                     .iter()
-                    .enumerate()
                     .cycle()
-                    .skip(current_client + 1)
-                    .find(|(_, client)| self.is_visible(workspace, client)),
-                Dir::Down => self
-                    .clients
+                    .skip(position + 1)
+                    .take(ids.len())
+                    .find(|id| self.is_visible(workspace, &self.clients[**id])),
+                Dir::Down => ids
                     .iter()
-                    .enumerate()
                     .rev()
                     .cycle()
-                    .skip(self.clients.len() - current_client)
-                    .find(|(_, client)| self.is_visible(workspace, client)),
+                    .skip(ids.len() - position)
+                    .take(ids.len())
+                    .find(|id| self.is_visible(workspace, &self.clients[**id])),
             } {
-                self.set_focus_and_warp(index);
+                self.set_focus_and_warp(*id);
             };
         }
     }
@@ -239,22 +402,23 @@ impl Backend<'_> {
     pub fn focus_dir(&mut self, direction: Dir) {
         if let Some(current_client) = self.current_client {
             let workspace = self.monitors[self.current_monitor].get_current_workspace();
-            if let Some((index, _)) = match direction {
-                Dir::Up => self
-                    .clients
+            let ids = self.clients.ids();
+            let position = ids
+                .iter()
+                .position(|id| *id == current_client)
+                .unwrap_or_default();
+            if let Some(id) = match direction {
+                Dir::Up => ids
                     .iter()
-                    .enumerate()
-                    .skip(current_client + 1)
-                    .find(|(_, client)| self.is_visible(workspace, client)),
-                Dir::Down => self
-                    .clients
+                    .skip(position + 1)
+                    .find(|id| self.is_visible(workspace, &self.clients[**id])),
+                Dir::Down => ids
                     .iter()
-                    .enumerate()
                     .rev()
-                    .skip(self.clients.len() - current_client)
-                    .find(|(_, client)| self.is_visible(workspace, client)),
+                    .skip(ids.len() - position)
+                    .find(|id| self.is_visible(workspace, &self.clients[**id])),
             } {
-                self.set_focus_and_warp(index);
+                self.set_focus_and_warp(*id);
             } else {
                 self.focus_monitor(direction.opposite());
             }
@@ -264,8 +428,106 @@ impl Backend<'_> {
     }
 }
 
+// Resolve a layout given either by name, symbol or index.
+fn find_layout(layouts: &[Layout], layout: &str) -> CritResult<usize> {
+    layouts
+        .iter()
+        .position(|l| l.name == layout || l.symbol == layout)
+        .or_else(|| layout.parse::<usize>().ok())
+        .filter(|index| *index < layouts.len())
+        .ok_or_else(|| CritError::Other(format!("Unknown layout: {}", layout)))
+}
+
+// Queue a signal to be handled by the backend. If reply is given, it receives the outcome once
+// the signal has been handled.
+pub fn send(signal: Signal, reply: Option<SignalReply>) -> CritResult<()> {
+    SIGNAL_QUEUE
+        .0
+        .send(SignalRequest { signal, reply })
+        .map_err(|_| CritError::Other("Signal queue is closed".to_owned()))
+}
+
+// Take the receiving end of the signal queue. Only the event loop should hold it, so this
+// returns None when called more than once.
+pub fn take_receiver() -> Option<UnboundedReceiver<SignalRequest>> {
+    SIGNAL_QUEUE.1.lock().unwrap().take()
+}
+
 lazy_static! {
-    // SIGNAL_STACK stores global signals that are executed accordingly in the backend.
-    // This system allows signals to be freely added and executed externally.
-    pub static ref SIGNAL_STACK: Arc<Mutex<Vec<Signal>>> = Arc::new(Mutex::new(Vec::new()));
+    // SIGNAL_QUEUE stores global signals in the order they are issued. They are executed
+    // accordingly in the backend. This system allows signals to be freely added and executed
+    // externally.
+    static ref SIGNAL_QUEUE: (
+        UnboundedSender<SignalRequest>,
+        Mutex<Option<UnboundedReceiver<SignalRequest>>>
+    ) = {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (sender, Mutex::new(Some(receiver)))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dir, Signal};
+    use crate::config;
+
+    #[test]
+    fn deserialize_signals() {
+        assert!(matches!(
+            serde_json::from_str::<Signal>("\"KillClient\""),
+            Ok(Signal::KillClient)
+        ));
+        assert!(matches!(
+            serde_json::from_str::<Signal>("{\"ChangeWorkspace\":2}"),
+            Ok(Signal::ChangeWorkspace(2))
+        ));
+        assert!(matches!(
+            serde_json::from_str::<Signal>("{\"FocusStack\":\"Down\"}"),
+            Ok(Signal::FocusStack(Dir::Down))
+        ));
+        assert!(serde_json::from_str::<Signal>("\"Unknown\"").is_err());
+    }
+
+    #[test]
+    fn validate_signals() {
+        let layouts = &config::get_layouts()[..2];
+        assert!(Signal::ChangeWorkspace(0).validate(layouts).is_ok());
+        assert!(Signal::MoveToWorkspace(config::WORKSPACE_COUNT)
+            .validate(layouts)
+            .is_err());
+        assert!(Signal::SetLayout(1).validate(layouts).is_ok());
+        assert!(Signal::SetLayout(2).validate(layouts).is_err());
+        assert!(Signal::SetLayoutByName(layouts[1].name.clone())
+            .validate(layouts)
+            .is_ok());
+        assert!(Signal::SetLayoutByName("nope".to_owned())
+            .validate(layouts)
+            .is_err());
+    }
+
+    #[test]
+    fn find_layouts() {
+        let layouts = config::get_layouts();
+        assert_eq!(super::find_layout(&layouts, &layouts[1].name).unwrap(), 1);
+        assert_eq!(super::find_layout(&layouts, &layouts[1].symbol).unwrap(), 1);
+        assert_eq!(super::find_layout(&layouts, "0").unwrap(), 0);
+        assert!(super::find_layout(&layouts, "unknown").is_err());
+        assert!(super::find_layout(&layouts, &layouts.len().to_string()).is_err());
+    }
+
+    #[test]
+    fn signals_are_ordered() {
+        let mut receiver = super::take_receiver().unwrap();
+        super::send(Signal::MoveToWorkspace(2), None).unwrap();
+        super::send(Signal::ChangeWorkspace(2), None).unwrap();
+        assert!(matches!(
+            receiver.try_recv().map(|request| request.signal),
+            Ok(Signal::MoveToWorkspace(2))
+        ));
+        assert!(matches!(
+            receiver.try_recv().map(|request| request.signal),
+            Ok(Signal::ChangeWorkspace(2))
+        ));
+        assert!(super::take_receiver().is_none());
+    }
 }
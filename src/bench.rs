@@ -0,0 +1,56 @@
+// Helpers for the benchmarks in benches, which are not part of the API of critwm.
+use crate::{
+    backend::{
+        client::{Client, Clients, WindowGeometry},
+        monitor::{Margins, MonitorGeometry},
+    },
+    layouts::tile::tile,
+    settings::Settings,
+};
+use x11_dl::xlib;
+
+// Clients with the windows 1 to count on the first workspace of the first monitor, where every
+// tenth client is a transient of the client before it.
+pub fn clients(count: xlib::Window) -> Clients {
+    let mut clients = Clients::default();
+    for window in 1..=count {
+        let mut client = Client::new(WindowGeometry::default(), 0, 0);
+        client.window = window;
+        if window % 10 == 0 {
+            client.transient_for = Some(window - 1);
+        }
+        clients.insert(client);
+    }
+    clients
+}
+
+// The bookkeeping that handling an event about a client does without the requests to the X
+// server: find the client, raise it, arrange its workspace and restack the shown clients.
+pub fn dispatch(clients: &mut Clients, event: &xlib::XEvent, settings: &Settings) {
+    let Some(id) = clients.event_client(event) else {
+        return;
+    };
+    clients.raise(id);
+    let (monitor, workspace) = (clients[id].monitor, clients[id].workspace);
+    let geometries = tile(
+        monitor,
+        workspace,
+        &MonitorGeometry::new(0, 0, 1920, 1080),
+        &clients.iter().collect::<Vec<_>>(),
+        &Margins::default(),
+        settings,
+    );
+    for (id, geometry) in clients.ids().into_iter().zip(geometries) {
+        if *clients[id].get_geometry() != geometry {
+            *clients[id].get_geometry_mut() = geometry;
+        }
+    }
+    let (above, transients) =
+        clients.restacking(|client| client.monitor == monitor && client.workspace == workspace);
+    for id in above {
+        clients.raise(id);
+    }
+    for (id, parent) in transients {
+        clients.place_above(id, parent);
+    }
+}
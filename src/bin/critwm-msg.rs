@@ -0,0 +1,123 @@
+use clap::{Parser, Subcommand};
+use critwm::{
+    backend::signal::{Dir, Signal},
+    config,
+    error::{CritError, CritResult},
+    socket::{self, Reply},
+};
+use std::process;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+};
+
+async fn start(args: Args) -> CritResult<Reply> {
+    let signal = args.command.into_signal()?;
+    let mut stream = UnixStream::connect(socket::command_socket_path()).await?;
+    let mut json = serde_json::to_string(&signal)?;
+    json.push('\n');
+    stream.write_all(json.as_bytes()).await?;
+    let mut reader = BufReader::new(stream).lines();
+    match reader.next_line().await? {
+        Some(line) => {
+            println!("{line}");
+            Ok(serde_json::from_str(&line)?)
+        }
+        None => Err(CritError::Other(
+            "Connection closed without a reply".to_owned(),
+        )),
+    }
+}
+
+// Convert a workspace number as displayed to the user (starting from 1) to a workspace index.
+fn workspace_index(workspace: usize) -> CritResult<usize> {
+    if (1..=config::WORKSPACE_COUNT).contains(&workspace) {
+        Ok(workspace - 1)
+    } else {
+        Err(CritError::Other(format!(
+            "Workspace must be between 1 and {}",
+            config::WORKSPACE_COUNT
+        )))
+    }
+}
+
+/// Send commands to a running critwm
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Change to the given workspace
+    Workspace { workspace: usize },
+    /// Move the focused client to the given workspace
+    MoveToWorkspace { workspace: usize },
+    /// Set the layout of the focused monitor by name or index
+    Layout { layout: String },
+    /// Focus the next client in the stack
+    Focus {
+        #[arg(value_enum)]
+        direction: Dir,
+    },
+    /// Focus the next monitor
+    FocusMonitor {
+        #[arg(value_enum)]
+        direction: Dir,
+    },
+    /// Focus the next client in the stack, or the next monitor if there is none
+    FocusDir {
+        #[arg(value_enum)]
+        direction: Dir,
+    },
+    /// Kill the focused client
+    Kill,
+    /// Toggle floating of the focused client
+    ToggleFloating,
+    /// Toggle the bar of the focused monitor
+    ToggleBar,
+    /// Reload the configuration file
+    Reload,
+    /// Restart critwm in place, keeping every window where it is
+    Restart,
+    /// Quit critwm
+    Quit,
+}
+
+impl Command {
+    fn into_signal(self) -> CritResult<Signal> {
+        Ok(match self {
+            Self::Workspace { workspace } => Signal::ChangeWorkspace(workspace_index(workspace)?),
+            Self::MoveToWorkspace { workspace } => {
+                Signal::MoveToWorkspace(workspace_index(workspace)?)
+            }
+            Self::Layout { layout } => Signal::SetLayoutByName(layout),
+            Self::Focus { direction } => Signal::FocusStack(direction),
+            Self::FocusMonitor { direction } => Signal::FocusMon(direction),
+            Self::FocusDir { direction } => Signal::FocusDir(direction),
+            Self::Kill => Signal::KillClient,
+            Self::ToggleFloating => Signal::ToggleFloating,
+            Self::ToggleBar => Signal::ToggleBar,
+            Self::Reload => Signal::Reload,
+            Self::Restart => Signal::Restart,
+            Self::Quit => Signal::Quit,
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    match start(args).await {
+        Ok(reply) => {
+            // The reply has already been printed, only the exit code is left to set.
+            process::exit(if reply.success { 0 } else { 1 });
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1)
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     backend::signal::{Dir, Signal},
     layouts::{self, Layout},
+    rules::Rule,
     util::{self, Action, Key, ModMask},
 };
 use std::collections::HashMap;
@@ -11,52 +12,72 @@ pub const WORKSPACES: [&str; WORKSPACE_COUNT] = ["1", "2", "3", "4", "5", "6", "
 const TAG_KEYS: [u32; WORKSPACE_COUNT] = [XK_1, XK_2, XK_3, XK_4, XK_5, XK_6, XK_7, XK_8, XK_9];
 
 pub const GAP: i32 = 15;
-pub const BAR_MARGIN: i32 = 24;
 pub const BORDER: i32 = 1;
 pub const BORDER_FOCUSED_COLOR: u64 = 0xbbbbbb;
 pub const BORDER_NORMAL_COLOR: u64 = 0x222222;
+pub const BORDER_URGENT_COLOR: u64 = 0xff0000;
 
 pub const CURSOR_WARP: bool = false;
 
 pub const MODKEY: ModMask = Mod4Mask;
 const TERMINAL: &str = "st";
 
-pub fn get_keymap() -> HashMap<Key, Action> {
-    let mut keymap: Vec<(Key, Action)> = vec![
-        key!(MODKEY, XK_space, util::spawn("dmenu_run")),
-        key!(MODKEY, XK_Return, util::spawn(TERMINAL)),
-        key!(MODKEY, XK_j, util::signal(Signal::FocusStack(Dir::Down))),
-        key!(MODKEY, XK_k, util::signal(Signal::FocusStack(Dir::Up))),
-        key!(MODKEY, XK_w, util::signal(Signal::KillClient)),
-        key!(MODKEY, XK_s, util::signal(Signal::ToggleFloating)),
-        key!(MODKEY, XK_b, util::signal(Signal::ToggleBar)),
-        key!(MODKEY, XK_t, util::signal(Signal::SetLayout(0))),
-        key!(MODKEY, XK_f, util::signal(Signal::SetLayout(1))),
-        key!(MODKEY, XK_comma, util::signal(Signal::FocusMon(Dir::Down))),
-        key!(MODKEY, XK_period, util::signal(Signal::FocusMon(Dir::Up))),
-        key!(MODKEY | ShiftMask, XK_q, util::signal(Signal::Quit)),
+pub fn get_keys(modkey: ModMask) -> Vec<(Key, Action)> {
+    let mut keys: Vec<(Key, Action)> = vec![
+        key!(modkey, XK_space, util::spawn("dmenu_run")),
+        key!(modkey, XK_Return, util::spawn(TERMINAL)),
+        key!(modkey, XK_j, util::signal(Signal::FocusStack(Dir::Down))),
+        key!(modkey, XK_k, util::signal(Signal::FocusStack(Dir::Up))),
+        key!(modkey, XK_w, util::signal(Signal::KillClient)),
+        key!(modkey, XK_s, util::signal(Signal::ToggleFloating)),
+        key!(modkey, XK_b, util::signal(Signal::ToggleBar)),
+        key!(modkey, XK_t, util::signal(Signal::SetLayout(0))),
+        key!(modkey, XK_f, util::signal(Signal::SetLayout(1))),
+        key!(modkey, XK_comma, util::signal(Signal::FocusMon(Dir::Down))),
+        key!(modkey, XK_period, util::signal(Signal::FocusMon(Dir::Up))),
+        key!(modkey | ShiftMask, XK_r, util::signal(Signal::Reload)),
+        key!(
+            modkey | ControlMask | ShiftMask,
+            XK_r,
+            util::signal(Signal::Restart)
+        ),
+        key!(modkey | ShiftMask, XK_q, util::signal(Signal::Quit)),
     ];
     for (i, tag_key) in TAG_KEYS.iter().enumerate() {
         // Add workspace changing binds.
-        keymap.push(key!(
-            MODKEY,
+        keys.push(key!(
+            modkey,
             *tag_key,
             util::signal(Signal::ChangeWorkspace(i))
         ));
         // Add workspace moving binds.
-        keymap.push(key!(
-            MODKEY | ShiftMask,
+        keys.push(key!(
+            modkey | ShiftMask,
             *tag_key,
             util::signal(Signal::MoveToWorkspace(i))
         ));
     }
-    keymap.into_iter().collect::<HashMap<Key, Action>>()
+    keys
+}
+
+pub fn get_keymap(modkey: ModMask) -> HashMap<Key, Action> {
+    get_keys(modkey).into_iter().collect::<HashMap<Key, Action>>()
 }
 
 pub fn get_layouts() -> Vec<Layout> {
     vec![
         // First entry is default.
-        Layout::new("[]=", layouts::tile::tile),
-        Layout::new("><>", layouts::float::float),
+        Layout::new("tile", "[]=", layouts::tile::tile),
+        Layout::new("float", "><>", layouts::float::float),
     ]
 }
+
+// Rules applied to new windows, for example:
+// Rule {
+//     class: Some(Pattern::exact("Firefox")),
+//     actions: RuleActions { workspace: Some(1), ..Default::default() },
+//     ..Default::default()
+// }
+pub fn get_rules() -> Vec<Rule> {
+    Vec::new()
+}
@@ -1,3 +1,4 @@
+use crate::settings::ConfigError;
 use std::{fmt, io};
 use x11_dl::error as x11_error;
 
@@ -8,6 +9,7 @@ pub enum CritError {
     Open(x11_error::OpenError),
     Io(io::Error),
     SerdeJson(serde_json::Error),
+    Config(Vec<ConfigError>),
     Other(String),
 }
 
@@ -18,6 +20,10 @@ impl fmt::Display for CritError {
             Open(ref e) => e.fmt(f),
             Io(ref e) => e.fmt(f),
             SerdeJson(ref e) => e.fmt(f),
+            Config(ref errors) => {
+                let errors: Vec<String> = errors.iter().map(ConfigError::to_string).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
             Other(ref s) => write!(f, "{}", s),
         }
     }
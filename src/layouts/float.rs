@@ -1,18 +1,21 @@
 use crate::{
     backend::{
         client::{Client, WindowGeometry},
-        monitor::MonitorGeometry,
+        monitor::{Margins, MonitorGeometry},
     },
-    layouts::{self, BarStatus},
+    layouts,
+    settings::Settings,
 };
 
 pub fn float(
     monitor_index: usize,
     workspace: usize,
     monitor_geometry: &MonitorGeometry,
-    clients: &[Client],
-    _bar_status: &BarStatus,
+    clients: &[&Client],
+    margins: &Margins,
+    _settings: &Settings,
 ) -> Vec<WindowGeometry> {
+    let work_area = layouts::get_work_area(monitor_geometry, margins);
     clients
         .iter()
         .map(|client| {
@@ -21,8 +24,8 @@ pub fn float(
             if layouts::is_arrangeable(client, monitor_index, workspace)
                 && !monitor_geometry.has_window(&geometry)
             {
-                geometry.x = monitor_geometry.x;
-                geometry.y = monitor_geometry.y;
+                geometry.x = work_area.x;
+                geometry.y = work_area.y;
             }
             geometry
         })
@@ -3,59 +3,40 @@ pub mod tile;
 
 use crate::{
     backend::{
-        client::{Client, WindowGeometry},
-        monitor::MonitorGeometry,
+        client::{Client, WindowGeometry, WindowState},
+        monitor::{Margins, MonitorGeometry},
     },
-    config,
+    settings::Settings,
 };
-use serde::{Serialize, Serializer};
+use serde::Serialize;
 use std::fmt;
 
-#[derive(Debug)]
-pub enum BarStatus {
-    Show,
-    Hide,
-}
-
-impl Serialize for BarStatus {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_bool(match self {
-            Self::Show => true,
-            Self::Hide => false,
-        })
-    }
-}
-
-impl Default for BarStatus {
-    fn default() -> Self {
-        Self::Show
-    }
-}
-
 fn is_arrangeable(client: &Client, monitor_index: usize, workspace: usize) -> bool {
     // Layouts should only modify the geometry of clients that are arrangeable.
     !client.fullscreen
         && !client.floating
+        && !client.has_state(WindowState::Hidden)
         && !client.dock
         && client.monitor == monitor_index
         && client.workspace == workspace
 }
 
-fn get_bar_margin(bar_status: &BarStatus) -> i32 {
-    match bar_status {
-        BarStatus::Show => config::BAR_MARGIN,
-        BarStatus::Hide => 0,
-    }
+// Area of the monitor that is not reserved by docks.
+fn get_work_area(monitor_geometry: &MonitorGeometry, margins: &Margins) -> MonitorGeometry {
+    MonitorGeometry::new(
+        monitor_geometry.x + margins.left,
+        monitor_geometry.y + margins.top,
+        monitor_geometry.width - margins.left - margins.right,
+        monitor_geometry.height - margins.top - margins.bottom,
+    )
 }
 
 pub type LayoutFunc =
-    fn(usize, usize, &MonitorGeometry, &[Client], &BarStatus) -> Vec<WindowGeometry>;
+    fn(usize, usize, &MonitorGeometry, &[&Client], &Margins, &Settings) -> Vec<WindowGeometry>;
 
 #[derive(Serialize, Clone)]
 pub struct Layout {
+    pub name: String,
     pub symbol: String,
     #[serde(skip_serializing)]
     pub func: LayoutFunc,
@@ -69,8 +50,9 @@ impl fmt::Debug for Layout {
 }
 
 impl Layout {
-    pub fn new(symbol: &str, func: LayoutFunc) -> Self {
+    pub fn new(name: &str, symbol: &str, func: LayoutFunc) -> Self {
         Self {
+            name: name.to_owned(),
             symbol: symbol.to_owned(),
             func,
         }
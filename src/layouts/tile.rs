@@ -1,18 +1,19 @@
 use crate::{
     backend::{
         client::{Client, WindowGeometry},
-        monitor::MonitorGeometry,
+        monitor::{Margins, MonitorGeometry},
     },
-    config,
-    layouts::{self, BarStatus},
+    layouts,
+    settings::Settings,
 };
 
 pub fn tile(
     monitor_index: usize,
     workspace: usize,
     monitor_geometry: &MonitorGeometry,
-    clients: &[Client],
-    bar_status: &BarStatus,
+    clients: &[&Client],
+    margins: &Margins,
+    settings: &Settings,
 ) -> Vec<WindowGeometry> {
     let mut stack_indices = Vec::new();
     for (index, _) in clients
@@ -28,39 +29,38 @@ pub fn tile(
         .cloned()
         .collect::<Vec<WindowGeometry>>();
     if !stack_indices.is_empty() {
-        let bar_margin = layouts::get_bar_margin(bar_status);
-        const DOUBLE_GAP: i32 = config::GAP * 2;
-        let (x, y, width, height) = (
-            monitor_geometry.x,
-            monitor_geometry.y + bar_margin,
-            monitor_geometry.width,
-            monitor_geometry.height - bar_margin,
-        );
+        let gap = settings.gap;
+        let double_gap = gap * 2;
+        let MonitorGeometry {
+            x,
+            y,
+            width,
+            height,
+        } = layouts::get_work_area(monitor_geometry, margins);
         // The main window is the window that was added last.
         let main = stack_indices[stack_indices.len() - 1];
-        window_geometry[main].x = x + config::GAP;
-        window_geometry[main].y = y + config::GAP;
-        window_geometry[main].height = height - DOUBLE_GAP;
+        window_geometry[main].x = x + gap;
+        window_geometry[main].y = y + gap;
+        window_geometry[main].height = height - double_gap;
         if stack_indices.len() > 1 {
             let middle_x = width / 2;
-            let stack_width = (width - (3 * config::GAP)) / 2;
-            let stack_height = (height - (stack_indices.len() as i32 * config::GAP))
-                / (stack_indices.len() - 1) as i32;
+            let stack_width = (width - (3 * gap)) / 2;
+            let stack_height =
+                (height - (stack_indices.len() as i32 * gap)) / (stack_indices.len() - 1) as i32;
             window_geometry[main].width = stack_width;
             // Pop out main window.
             stack_indices.pop();
             // Set position of children.
             for (i, geometry_index) in stack_indices.iter().rev().enumerate() {
                 let geometry_index = *geometry_index;
-                window_geometry[geometry_index].x = x + middle_x + (config::GAP / 2);
-                window_geometry[geometry_index].y =
-                    y + (i as i32 * (config::GAP + stack_height)) + config::GAP;
+                window_geometry[geometry_index].x = x + middle_x + (gap / 2);
+                window_geometry[geometry_index].y = y + (i as i32 * (gap + stack_height)) + gap;
                 window_geometry[geometry_index].width = stack_width;
                 window_geometry[geometry_index].height = stack_height;
             }
         } else {
             // Only one main window with no children.
-            window_geometry[main].width = width - DOUBLE_GAP;
+            window_geometry[main].width = width - double_gap;
         }
     }
     window_geometry
@@ -72,10 +72,10 @@ mod tests {
     use crate::{
         backend::{
             client::{Client, WindowGeometry},
-            monitor::MonitorGeometry,
+            monitor::{Margins, MonitorGeometry},
         },
         config,
-        layouts::BarStatus,
+        settings::Settings,
     };
 
     #[test]
@@ -93,8 +93,9 @@ mod tests {
                 monitor_index,
                 workspace,
                 &monitor_geometry,
-                &clients,
-                &BarStatus::Hide
+                &clients.iter().collect::<Vec<_>>(),
+                &Margins::default(),
+                &Settings::default()
             ),
             vec![WindowGeometry::new(
                 config::GAP,
@@ -117,8 +118,9 @@ mod tests {
                 monitor_index,
                 workspace,
                 &monitor_geometry,
-                &clients,
-                &BarStatus::Hide
+                &clients.iter().collect::<Vec<_>>(),
+                &Margins::default(),
+                &Settings::default()
             ),
             vec![WindowGeometry::default()]
         );
@@ -136,8 +138,9 @@ mod tests {
                 monitor_index,
                 workspace,
                 &monitor_geometry,
-                &clients,
-                &BarStatus::Hide
+                &clients.iter().collect::<Vec<_>>(),
+                &Margins::default(),
+                &Settings::default()
             ),
             vec![WindowGeometry::default()]
         );
@@ -160,8 +163,9 @@ mod tests {
                 monitor_index,
                 workspace,
                 &monitor_geometry,
-                &clients,
-                &BarStatus::Hide
+                &clients.iter().collect::<Vec<_>>(),
+                &Margins::default(),
+                &Settings::default()
             ),
             vec![
                 WindowGeometry::new(
@@ -189,4 +193,34 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn reserved_margins() {
+        let monitor_index = 0;
+        let workspace = 0;
+        let monitor_geometry = MonitorGeometry::new(0, 0, 1920, 1080);
+        let clients = [Client::new(
+            WindowGeometry::default(),
+            monitor_index,
+            workspace,
+        )];
+        let margins = Margins::new(0, 48, 24, 0);
+        assert_eq!(
+            tile(
+                monitor_index,
+                workspace,
+                &monitor_geometry,
+                &clients.iter().collect::<Vec<_>>(),
+                &margins,
+                &Settings::default()
+            ),
+            vec![WindowGeometry::new(
+                config::GAP,
+                margins.top + config::GAP,
+                monitor_geometry.width - margins.right - (2 * config::GAP),
+                monitor_geometry.height - margins.top - (2 * config::GAP),
+                0
+            )]
+        );
+    }
 }
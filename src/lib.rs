@@ -4,8 +4,12 @@ extern crate log;
 #[macro_use]
 pub mod util;
 pub mod backend;
+#[doc(hidden)]
+pub mod bench;
 pub mod error;
 pub mod layouts;
+pub mod rules;
+pub mod settings;
 pub mod socket;
 
 pub mod config {
@@ -1,61 +1,207 @@
 #[macro_use]
 extern crate log;
 
+use clap::Parser;
 use critwm::{
-    backend::Backend,
+    backend::{session, signal, Backend},
     error::{CritError, CritResult},
-    socket::{self, StateSocket},
+    settings::Settings,
+    socket::{self, CommandSocket, StateSocket},
+    util,
 };
-use std::{path::PathBuf, process, ptr};
-use x11_dl::{xinerama, xlib};
+use std::{
+    env,
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{self, Command},
+    ptr,
+};
+use tokio::io::unix::AsyncFd;
+use x11_dl::{xinerama, xlib, xrandr};
+
+// Load settings from the configuration file, falling back to the compiled configuration if the
+// file does not exist or is invalid.
+fn load_settings(xlib: &xlib::Xlib, path: Option<&PathBuf>, explicit: bool) -> Settings {
+    match path {
+        Some(path) if explicit || path.exists() => match Settings::load(xlib, path) {
+            Ok(settings) => {
+                info!("Using configuration file {:?}", path);
+                settings
+            }
+            Err(e) => {
+                error!("Failed to load configuration file {:?}: {}", path, e);
+                Settings::default()
+            }
+        },
+        _ => Settings::default(),
+    }
+}
 
-async fn start() -> CritResult<()> {
+async fn start(args: Args) -> CritResult<()> {
     // Open xlib.
     let xlib = xlib::Xlib::open()?;
+    let explicit = args.config.is_some();
+    let settings_path = args.config.or_else(Settings::default_path);
+    let settings = load_settings(&xlib, settings_path.as_ref(), explicit);
     unsafe { (xlib.XSetErrorHandler)(Some(Backend::xerror)) };
+    util::reap_children();
     // Open display.
     let display = unsafe { (xlib.XOpenDisplay)(ptr::null()) };
     if display.is_null() {
         return Err(CritError::Other("Display is null.".to_owned()));
     }
-    let xinerama_xlib = xinerama::Xlib::open()?;
-    if unsafe { (xinerama_xlib.XineramaIsActive)(display) } == 0 {
-        return Err(CritError::Other("Xinerama is not active.".to_owned()));
-    }
-    let mut backend = unsafe { Backend::new(&xlib, &xinerama_xlib, display)? };
-    backend.initialize()?;
+    // Monitors are read from RandR, then from Xinerama. If neither is available, such as in Xephyr
+    // or Xvfb, the root window is used as a single monitor.
+    let xinerama_xlib = xinerama::Xlib::open()
+        .map_err(|e| warn!("Failed to open Xinerama: {}", e))
+        .ok()
+        .filter(|xinerama_xlib| {
+            let active = unsafe { (xinerama_xlib.XineramaIsActive)(display) } != 0;
+            if !active {
+                info!("Xinerama is not active");
+            }
+            active
+        });
+    let xrandr = xrandr::Xrandr::open()
+        .map_err(|e| warn!("Failed to open RandR: {}", e))
+        .ok();
+    let mut backend = unsafe {
+        Backend::new(
+            &xlib,
+            xinerama_xlib.as_ref(),
+            xrandr.as_ref(),
+            display,
+            settings,
+            settings_path,
+        )?
+    };
+    backend.initialize(args.restarted)?;
     backend.grab_keys();
     backend.grab_buttons();
     run(&mut backend).await?;
+    if backend.is_restarting() {
+        info!("Restarting critwm");
+        let e = exec_self();
+        // Clients on hidden workspaces were left unmapped for the new instance.
+        error!("Failed to restart critwm, mapping every client: {}", e);
+        if let Err(e) = session::map_windows(&xlib, &backend.client_windows()) {
+            error!("Failed to map clients: {}", e);
+        }
+        return Err(e);
+    }
     Ok(())
 }
 
+// Replace the process with a new instance of critwm started with the same arguments, telling it
+// to restore the session. Only returns if the new instance could not be started.
+fn exec_self() -> CritError {
+    match env::current_exe() {
+        Ok(exe) => Command::new(exe)
+            .args(env::args_os().skip(1).filter(|arg| arg != RESTARTED_ARG))
+            .arg(RESTARTED_ARG)
+            .exec()
+            .into(),
+        Err(e) => e.into(),
+    }
+}
+
 async fn run(backend: &mut Backend<'_>) -> CritResult<()> {
     let mut state_socket = StateSocket::new(PathBuf::from(socket::SOCKET_PATH));
     state_socket.listen().await?;
+    let mut command_socket = CommandSocket::new(socket::command_socket_path());
+    command_socket.listen().await?;
+    // Wait on the X connection instead of blocking in XNextEvent so that signals pushed from
+    // outside an X event are handled immediately.
+    let connection = AsyncFd::new(backend.connection_fd())?;
+    let mut signals = signal::take_receiver()
+        .ok_or_else(|| CritError::Other("Signal queue is already taken.".to_owned()))?;
     loop {
-        if backend.handle_signal()? {
-            // Quit signal has been handled.
+        backend.handle_events()?;
+        if backend.handle_signals(&mut signals) {
+            // Quit or restart signal has been handled.
             break;
         }
         backend.handle_cursor();
-        backend.handle_event()?;
         state_socket.write(backend).await?;
+        backend.flush();
+        if backend.has_queued_events() {
+            // Handling signals may have read events from the connection.
+            continue;
+        }
+        tokio::select! {
+            guard = connection.readable() => guard?.clear_ready(),
+            Some(request) = signals.recv() => {
+                if backend.handle_signal(request) {
+                    // Quit or restart signal has been handled.
+                    break;
+                }
+            }
+        }
     }
     state_socket.close().await?;
+    command_socket.close().await?;
     Ok(())
 }
 
+// Check the configuration file and print every problem found. Returns true if it is valid.
+fn check_config(path: Option<PathBuf>) -> CritResult<bool> {
+    let path = path
+        .or_else(Settings::default_path)
+        .ok_or_else(|| CritError::Other("No configuration file found.".to_owned()))?;
+    // Only the library is needed to resolve key names, the display is never opened.
+    let xlib = xlib::Xlib::open()?;
+    let errors = Settings::check(&xlib, &path);
+    for error in &errors {
+        match error.line {
+            Some(line) => println!("{}:{}: {}", path.display(), line, error.message),
+            None => println!("{}: {}", path.display(), error.message),
+        }
+    }
+    if errors.is_empty() {
+        println!("{}: configuration is valid", path.display());
+    }
+    Ok(errors.is_empty())
+}
+
+/// A tiling window manager for X
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the configuration file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Check the configuration file for problems and exit
+    #[arg(long, value_name = "PATH")]
+    check_config: Option<Option<PathBuf>>,
+
+    // Passed by a restart, so that the session of the previous instance is restored.
+    #[arg(long, hide = true)]
+    restarted: bool,
+}
+
+const RESTARTED_ARG: &str = "--restarted";
+
 #[tokio::main]
 async fn main() {
+    let args = Args::parse();
     env_logger::init();
+    if let Some(path) = args.check_config {
+        match check_config(path.or(args.config)) {
+            Ok(valid) => process::exit(if valid { 0 } else { 1 }),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1)
+            }
+        }
+    }
     info!("Started critwm");
     if cfg!(feature = "custom_config") {
         info!("Using custom configuration");
     } else {
         info!("Using default configuration");
     }
-    match start().await {
+    match start(args).await {
         Ok(_) => {
             info!("Closed critwm successfully");
             process::exit(0);
@@ -0,0 +1,186 @@
+use regex::Regex;
+use serde::Serialize;
+
+// A pattern matched against a window property, either the exact value or a regular expression.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Exact(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn exact(value: &str) -> Self {
+        Self::Exact(value.to_owned())
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self::Regex(Regex::new(pattern)?))
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Self::Exact(exact) => exact == value,
+            Self::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+// Properties of a window that rules are matched against.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WindowProperties {
+    // Class and instance are read from WM_CLASS.
+    pub class: Option<String>,
+    pub instance: Option<String>,
+    // Title is read from _NET_WM_NAME, falling back to WM_NAME.
+    pub title: Option<String>,
+    // Role is read from WM_WINDOW_ROLE.
+    pub role: Option<String>,
+}
+
+// Geometry of a window relative to its monitor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+// A rule applies its actions to every new window that matches all of its given patterns.
+#[derive(Debug, Clone, Default)]
+pub struct Rule {
+    pub class: Option<Pattern>,
+    pub instance: Option<Pattern>,
+    pub title: Option<Pattern>,
+    pub role: Option<Pattern>,
+    pub actions: RuleActions,
+}
+
+impl Rule {
+    pub fn matches(&self, properties: &WindowProperties) -> bool {
+        let matches = |pattern: &Option<Pattern>, value: &Option<String>| match pattern {
+            Some(pattern) => value.as_deref().is_some_and(|value| pattern.matches(value)),
+            None => true,
+        };
+        matches(&self.class, &properties.class)
+            && matches(&self.instance, &properties.instance)
+            && matches(&self.title, &properties.title)
+            && matches(&self.role, &properties.role)
+    }
+}
+
+// Actions applied to a matching window. Actions that are None leave the window as it would be
+// without the rule.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleActions {
+    // Index of the workspace the window is placed on.
+    pub workspace: Option<usize>,
+    // Index of the monitor the window is placed on.
+    pub monitor: Option<usize>,
+    pub floating: Option<bool>,
+    pub fullscreen: Option<bool>,
+    // Initial geometry of the window, which only applies to floating windows.
+    pub geometry: Option<RuleGeometry>,
+    // Whether the window takes focus when it is mapped.
+    pub focus: Option<bool>,
+}
+
+impl RuleActions {
+    // Combine the actions of every rule matching the window. Rules are applied in order, so
+    // actions of later rules override those of earlier rules.
+    pub fn from_rules(rules: &[Rule], properties: &WindowProperties) -> Self {
+        rules.iter().filter(|rule| rule.matches(properties)).fold(
+            Self::default(),
+            |actions, rule| {
+                let rule = rule.actions.clone();
+                Self {
+                    workspace: rule.workspace.or(actions.workspace),
+                    monitor: rule.monitor.or(actions.monitor),
+                    floating: rule.floating.or(actions.floating),
+                    fullscreen: rule.fullscreen.or(actions.fullscreen),
+                    geometry: rule.geometry.or(actions.geometry),
+                    focus: rule.focus.or(actions.focus),
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pattern, Rule, RuleActions, WindowProperties};
+
+    fn properties(class: &str, title: &str) -> WindowProperties {
+        WindowProperties {
+            class: Some(class.to_owned()),
+            instance: Some(class.to_lowercase()),
+            title: Some(title.to_owned()),
+            role: None,
+        }
+    }
+
+    #[test]
+    fn match_patterns() {
+        let rule = Rule {
+            class: Some(Pattern::exact("Firefox")),
+            title: Some(Pattern::regex("^Mozilla").unwrap()),
+            ..Default::default()
+        };
+        assert!(rule.matches(&properties("Firefox", "Mozilla Firefox")));
+        assert!(!rule.matches(&properties("Firefox", "Private Browsing")));
+        assert!(!rule.matches(&properties("firefox", "Mozilla Firefox")));
+        // A pattern never matches a missing property.
+        let rule = Rule {
+            role: Some(Pattern::regex(".*").unwrap()),
+            ..Default::default()
+        };
+        assert!(!rule.matches(&properties("Firefox", "Mozilla Firefox")));
+        // A rule without patterns matches every window.
+        assert!(Rule::default().matches(&WindowProperties::default()));
+    }
+
+    #[test]
+    fn later_rules_override() {
+        let rules = vec![
+            Rule {
+                class: Some(Pattern::exact("Firefox")),
+                actions: RuleActions {
+                    workspace: Some(1),
+                    floating: Some(true),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Rule {
+                title: Some(Pattern::regex("Picture-in-Picture").unwrap()),
+                actions: RuleActions {
+                    workspace: Some(2),
+                    focus: Some(false),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            RuleActions::from_rules(&rules, &properties("Firefox", "Mozilla Firefox")),
+            RuleActions {
+                workspace: Some(1),
+                floating: Some(true),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            RuleActions::from_rules(&rules, &properties("Firefox", "Picture-in-Picture")),
+            RuleActions {
+                workspace: Some(2),
+                floating: Some(true),
+                focus: Some(false),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            RuleActions::from_rules(&rules, &properties("Chromium", "Chromium")),
+            RuleActions::default()
+        );
+    }
+}
@@ -0,0 +1,714 @@
+use crate::{
+    backend::signal::Signal,
+    config,
+    error::{CritError, CritResult},
+    layouts::Layout,
+    rules::{Pattern, Rule, RuleActions, RuleGeometry},
+    util::{self, Action, Key, ModMask},
+};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+use toml::Spanned;
+use x11_dl::xlib;
+
+// Settings read at runtime from the configuration file. Values that are not given in the file
+// fall back to the compiled configuration.
+pub struct Settings {
+    pub gap: i32,
+    pub border: i32,
+    pub border_focused_color: u64,
+    pub border_normal_color: u64,
+    pub border_urgent_color: u64,
+    pub cursor_warp: bool,
+    pub modkey: ModMask,
+    pub workspaces: Vec<String>,
+    pub layouts: Vec<Layout>,
+    pub key_map: HashMap<Key, Action>,
+    pub rules: Vec<Rule>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            gap: config::GAP,
+            border: config::BORDER,
+            border_focused_color: config::BORDER_FOCUSED_COLOR,
+            border_normal_color: config::BORDER_NORMAL_COLOR,
+            border_urgent_color: config::BORDER_URGENT_COLOR,
+            cursor_warp: config::CURSOR_WARP,
+            modkey: config::MODKEY,
+            workspaces: config::WORKSPACES.iter().map(|w| w.to_string()).collect(),
+            layouts: config::get_layouts(),
+            key_map: config::get_keymap(config::MODKEY),
+            rules: config::get_rules(),
+        }
+    }
+}
+
+impl Settings {
+    // Default location of the configuration file.
+    // This is "$XDG_CONFIG_HOME/critwm/critwm.toml" or "$HOME/.config/critwm/critwm.toml".
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|path| path.join("critwm").join("critwm.toml"))
+    }
+
+    pub fn load(xlib: &xlib::Xlib, path: &Path) -> CritResult<Self> {
+        let contents = fs::read_to_string(path)?;
+        Self::parse(xlib, &contents)
+    }
+
+    // Parse settings, returning every problem found if the contents are invalid.
+    pub fn parse(xlib: &xlib::Xlib, contents: &str) -> CritResult<Self> {
+        let mut checker = Checker::new(contents);
+        match checker.parse(xlib) {
+            Some(settings) if checker.errors.is_empty() => Ok(settings),
+            _ => Err(CritError::Config(checker.errors)),
+        }
+    }
+
+    // Report every problem in the given configuration file. This also reports keybindings that are
+    // bound more than once in the compiled configuration, which would otherwise be silently
+    // dropped from the keymap.
+    pub fn check(xlib: &xlib::Xlib, path: &Path) -> Vec<ConfigError> {
+        let (modkey, mut errors) = match Self::load(xlib, path) {
+            Ok(settings) => (settings.modkey, Vec::new()),
+            Err(CritError::Config(errors)) => (config::MODKEY, errors),
+            Err(e) => (config::MODKEY, vec![ConfigError::new(None, e.to_string())]),
+        };
+        let mut keys = HashSet::new();
+        for (key, _) in config::get_keys(modkey) {
+            if !keys.insert(key.clone()) {
+                errors.push(ConfigError::new(
+                    None,
+                    format!(
+                        "Keybinding {} is bound more than once in the compiled configuration",
+                        util::key_name(xlib, &key)
+                    ),
+                ));
+            }
+        }
+        // Problems without a line come last.
+        errors.sort_by_key(|error| error.line.unwrap_or(usize::MAX));
+        errors
+    }
+}
+
+// A problem found in the configuration file. The line is None if the problem is not tied to a
+// specific line.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(line: Option<usize>, message: String) -> Self {
+        Self { line, message }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+// Layout of the configuration file. Every field is optional.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SettingsFile {
+    gap: Option<Spanned<i32>>,
+    border: Option<Spanned<i32>>,
+    border_focused_color: Option<Spanned<String>>,
+    border_normal_color: Option<Spanned<String>>,
+    border_urgent_color: Option<Spanned<String>>,
+    cursor_warp: Option<bool>,
+    modkey: Option<Spanned<String>>,
+    workspaces: Option<Spanned<Vec<String>>>,
+    layouts: Option<Spanned<Vec<Spanned<String>>>>,
+    #[serde(default)]
+    keybindings: Vec<Spanned<KeyBinding>>,
+    #[serde(default)]
+    rules: Vec<RuleFile>,
+}
+
+// A keybinding either spawns a command or sends a signal. The key is given in the form
+// "Mod4+Shift+Return", optionally combined with the modifiers listed in modifiers.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyBinding {
+    #[serde(default)]
+    modifiers: Vec<Spanned<String>>,
+    key: Spanned<String>,
+    spawn: Option<String>,
+    signal: Option<Spanned<Signal>>,
+}
+
+// A rule matches windows by any of class, instance, title and role, and applies the given actions
+// to them. The workspace is given either by number, starting from 1, or by name.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    class: Option<Spanned<PatternFile>>,
+    instance: Option<Spanned<PatternFile>>,
+    title: Option<Spanned<PatternFile>>,
+    role: Option<Spanned<PatternFile>>,
+    workspace: Option<Spanned<WorkspaceFile>>,
+    monitor: Option<usize>,
+    floating: Option<bool>,
+    fullscreen: Option<bool>,
+    geometry: Option<Spanned<GeometryFile>>,
+    focus: Option<bool>,
+}
+
+// Patterns are either an exact string or a regular expression given as { regex = "..." }.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PatternFile {
+    Exact(String),
+    Regex { regex: String },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WorkspaceFile {
+    Number(usize),
+    Name(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GeometryFile {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+// Checker resolves the configuration file into settings while collecting every problem it finds,
+// so that they can all be reported at once.
+struct Checker<'a> {
+    contents: &'a str,
+    errors: Vec<ConfigError>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(contents: &'a str) -> Self {
+        Self {
+            contents,
+            errors: Vec::new(),
+        }
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.contents[..offset.min(self.contents.len())]
+            .matches('\n')
+            .count()
+            + 1
+    }
+
+    fn report(&mut self, offset: usize, message: String) {
+        self.errors
+            .push(ConfigError::new(Some(self.line(offset)), message));
+    }
+
+    // Resolve a spanned value, reporting the error on the value's line if it is invalid.
+    fn resolve<T, U>(
+        &mut self,
+        value: Spanned<T>,
+        resolve: impl FnOnce(T) -> Result<U, String>,
+    ) -> Option<U> {
+        let offset = value.span().start;
+        match resolve(value.into_inner()) {
+            Ok(value) => Some(value),
+            Err(message) => {
+                self.report(offset, message);
+                None
+            }
+        }
+    }
+
+    fn parse(&mut self, xlib: &xlib::Xlib) -> Option<Settings> {
+        let file: SettingsFile = match toml::from_str(self.contents) {
+            Ok(file) => file,
+            Err(e) => {
+                let offset = e.span().map(|span| span.start).unwrap_or_default();
+                self.report(offset, e.message().to_owned());
+                return None;
+            }
+        };
+        let modkey = match file.modkey {
+            Some(name) => self.resolve(name, |name| parse_modifier(&name)),
+            None => Some(config::MODKEY),
+        };
+        let workspaces = match file.workspaces {
+            Some(workspaces) => self.resolve(workspaces, |workspaces| {
+                if workspaces.len() == config::WORKSPACE_COUNT {
+                    Ok(workspaces)
+                } else {
+                    Err(format!(
+                        "Expected {} workspaces but found {}",
+                        config::WORKSPACE_COUNT,
+                        workspaces.len()
+                    ))
+                }
+            }),
+            None => Some(config::WORKSPACES.iter().map(|w| w.to_string()).collect()),
+        };
+        let layouts = match file.layouts {
+            Some(names) if names.get_ref().is_empty() => {
+                self.report(
+                    names.span().start,
+                    "At least one layout is required".to_owned(),
+                );
+                None
+            }
+            Some(names) => names
+                .into_inner()
+                .into_iter()
+                .map(|name| self.resolve(name, |name| parse_layout(&name)))
+                .collect::<Vec<Option<Layout>>>()
+                .into_iter()
+                .collect::<Option<Vec<Layout>>>(),
+            None => Some(config::get_layouts()),
+        };
+        // Signals of keybindings are checked against the compiled layouts if the layouts are
+        // invalid.
+        let key_map = self.resolve_keybindings(
+            xlib,
+            modkey.unwrap_or(config::MODKEY),
+            layouts.as_deref().unwrap_or(&config::get_layouts()),
+            file.keybindings,
+        );
+        let mut non_negative = |value: Option<Spanned<i32>>, name: &str, default: i32| match value {
+            Some(value) => self.resolve(value, |value| {
+                if value >= 0 {
+                    Ok(value)
+                } else {
+                    Err(format!("{} must not be negative", name))
+                }
+            }),
+            None => Some(default),
+        };
+        let gap = non_negative(file.gap, "gap", config::GAP);
+        let border = non_negative(file.border, "border", config::BORDER);
+        let mut color = |value: Option<Spanned<String>>, default: u64| match value {
+            Some(value) => self.resolve(value, |value| parse_color(&value)),
+            None => Some(default),
+        };
+        let border_focused_color = color(file.border_focused_color, config::BORDER_FOCUSED_COLOR);
+        let border_normal_color = color(file.border_normal_color, config::BORDER_NORMAL_COLOR);
+        let border_urgent_color = color(file.border_urgent_color, config::BORDER_URGENT_COLOR);
+        // Workspace names in rules fall back to the compiled names if the workspaces are invalid.
+        let default_workspaces = Settings::default().workspaces;
+        let rules = self.resolve_rules(
+            workspaces.as_deref().unwrap_or(&default_workspaces),
+            file.rules,
+        );
+        Some(Settings {
+            gap: gap?,
+            border: border?,
+            border_focused_color: border_focused_color?,
+            border_normal_color: border_normal_color?,
+            border_urgent_color: border_urgent_color?,
+            cursor_warp: file.cursor_warp.unwrap_or(config::CURSOR_WARP),
+            modkey: modkey?,
+            workspaces: workspaces?,
+            layouts: layouts?,
+            key_map: key_map?,
+            rules: rules?,
+        })
+    }
+
+    // Rules in the file are applied after the compiled rules, so they take precedence.
+    fn resolve_rules(&mut self, workspaces: &[String], rules: Vec<RuleFile>) -> Option<Vec<Rule>> {
+        let mut resolved = config::get_rules();
+        let mut valid = true;
+        for rule in rules {
+            match self.resolve_rule(workspaces, rule) {
+                Some(rule) => resolved.push(rule),
+                None => valid = false,
+            }
+        }
+        valid.then_some(resolved)
+    }
+
+    fn resolve_rule(&mut self, workspaces: &[String], rule: RuleFile) -> Option<Rule> {
+        let mut pattern = |pattern: Option<Spanned<PatternFile>>| match pattern {
+            Some(pattern) => self.resolve(pattern, |pattern| match pattern {
+                PatternFile::Exact(value) => Ok(Some(Pattern::exact(&value))),
+                PatternFile::Regex { regex } => Pattern::regex(&regex)
+                    .map(Some)
+                    .map_err(|e| format!("Invalid regex {}: {}", regex, e)),
+            }),
+            None => Some(None),
+        };
+        let class = pattern(rule.class);
+        let instance = pattern(rule.instance);
+        let title = pattern(rule.title);
+        let role = pattern(rule.role);
+        let workspace = match rule.workspace {
+            Some(workspace) => self
+                .resolve(workspace, |workspace| {
+                    parse_workspace(workspaces, workspace)
+                })
+                .map(Some),
+            None => Some(None),
+        };
+        let geometry = match rule.geometry {
+            Some(geometry) => self
+                .resolve(geometry, |geometry| {
+                    if geometry.width > 0 && geometry.height > 0 {
+                        Ok(RuleGeometry {
+                            x: geometry.x,
+                            y: geometry.y,
+                            width: geometry.width,
+                            height: geometry.height,
+                        })
+                    } else {
+                        Err("Geometry width and height must be positive".to_owned())
+                    }
+                })
+                .map(Some),
+            None => Some(None),
+        };
+        Some(Rule {
+            class: class?,
+            instance: instance?,
+            title: title?,
+            role: role?,
+            actions: RuleActions {
+                workspace: workspace?,
+                monitor: rule.monitor,
+                floating: rule.floating,
+                fullscreen: rule.fullscreen,
+                geometry: geometry?,
+                focus: rule.focus,
+            },
+        })
+    }
+
+    // Keybindings in the file are added to the default keybindings, replacing any default
+    // keybinding with the same key. A key may only be bound once in the file.
+    fn resolve_keybindings(
+        &mut self,
+        xlib: &xlib::Xlib,
+        modkey: ModMask,
+        layouts: &[Layout],
+        keybindings: Vec<Spanned<KeyBinding>>,
+    ) -> Option<HashMap<Key, Action>> {
+        let mut key_map = config::get_keymap(modkey);
+        let mut lines = HashMap::new();
+        let mut valid = true;
+        for binding in keybindings {
+            let offset = binding.span().start;
+            match self.resolve_keybinding(xlib, layouts, binding.into_inner()) {
+                Some((key, action)) => {
+                    let line = self.line(offset);
+                    if let Some(previous) = lines.insert(key.clone(), line) {
+                        self.report(
+                            offset,
+                            format!(
+                                "Keybinding {} is already bound on line {}",
+                                util::key_name(xlib, &key),
+                                previous
+                            ),
+                        );
+                        valid = false;
+                    }
+                    key_map.insert(key, action);
+                }
+                None => valid = false,
+            }
+        }
+        valid.then_some(key_map)
+    }
+
+    fn resolve_keybinding(
+        &mut self,
+        xlib: &xlib::Xlib,
+        layouts: &[Layout],
+        binding: KeyBinding,
+    ) -> Option<(Key, Action)> {
+        let mut modifier = Some(0);
+        for name in binding.modifiers {
+            let mask = self.resolve(name, |name| parse_modifier(&name));
+            modifier = modifier.zip(mask).map(|(modifier, mask)| modifier | mask);
+        }
+        let offset = binding.key.span().start;
+        let key_name = binding.key.get_ref().clone();
+        let key = self.resolve(binding.key, |name| {
+            util::parse_key(xlib, &name).map_err(|e| e.to_string())
+        });
+        let action: Action = match (binding.spawn, binding.signal) {
+            (Some(command), None) => Box::new(move || util::spawn(&command)),
+            (None, Some(signal)) => {
+                // The signal is still checked against the layouts when it is sent, as the
+                // layouts may change on reload.
+                let signal = self.resolve(signal, |signal| {
+                    signal.validate(layouts).map_err(|e| e.to_string())?;
+                    Ok(signal)
+                })?;
+                Box::new(move || util::signal(signal.clone()))
+            }
+            _ => {
+                self.report(
+                    offset,
+                    format!(
+                        "Keybinding for {} requires exactly one of spawn or signal",
+                        key_name
+                    ),
+                );
+                return None;
+            }
+        };
+        let key = key?;
+        Some((Key::new(key.modifier | modifier?, key.sym), action))
+    }
+}
+
+fn parse_modifier(name: &str) -> Result<ModMask, String> {
+    util::modifier_from_name(name).ok_or_else(|| format!("Unknown modifier: {}", name))
+}
+
+// Layouts are selected by name from the compiled layouts.
+fn parse_layout(name: &str) -> Result<Layout, String> {
+    config::get_layouts()
+        .into_iter()
+        .find(|layout| layout.name == name)
+        .ok_or_else(|| format!("Unknown layout: {}", name))
+}
+
+// Resolve a workspace given by number, starting from 1, or by name to a workspace index.
+fn parse_workspace(workspaces: &[String], workspace: WorkspaceFile) -> Result<usize, String> {
+    match workspace {
+        WorkspaceFile::Number(number) if (1..=config::WORKSPACE_COUNT).contains(&number) => {
+            Ok(number - 1)
+        }
+        WorkspaceFile::Number(number) => Err(format!(
+            "Workspace must be between 1 and {} but found {}",
+            config::WORKSPACE_COUNT,
+            number
+        )),
+        WorkspaceFile::Name(name) => workspaces
+            .iter()
+            .position(|workspace| *workspace == name)
+            .ok_or_else(|| format!("Unknown workspace: {}", name)),
+    }
+}
+
+// Colors are given as hexadecimal RGB values such as "#bbbbbb".
+fn parse_color(value: &str) -> Result<u64, String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    match u64::from_str_radix(hex, 16) {
+        Ok(color) if hex.len() == 6 => Ok(color),
+        _ => Err(format!("Invalid color: {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Settings;
+    use crate::{
+        config,
+        error::CritError,
+        rules::{RuleActions, RuleGeometry, WindowProperties},
+        util::Key,
+    };
+    use x11_dl::{keysym::*, xlib};
+
+    fn parse(contents: &str) -> Option<Settings> {
+        let xlib = xlib::Xlib::open().unwrap();
+        Settings::parse(&xlib, contents).ok()
+    }
+
+    #[test]
+    fn empty_file() {
+        let settings = parse("").unwrap();
+        assert_eq!(settings.gap, config::GAP);
+        assert_eq!(settings.modkey, config::MODKEY);
+        assert_eq!(settings.layouts.len(), config::get_layouts().len());
+        assert_eq!(
+            settings.key_map.len(),
+            config::get_keymap(config::MODKEY).len()
+        );
+    }
+
+    #[test]
+    fn override_values() {
+        let settings = parse(
+            r##"
+            gap = 4
+            border_focused_color = "#ff0000"
+            modkey = "Mod1"
+            layouts = ["float"]
+
+            [[keybindings]]
+            modifiers = ["Mod1", "Shift"]
+            key = "Return"
+            spawn = "xterm"
+
+            [[keybindings]]
+            key = "Mod1+Control+F5"
+            signal = "Reload"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(settings.gap, 4);
+        assert_eq!(settings.border_focused_color, 0xff0000);
+        assert_eq!(settings.modkey, xlib::Mod1Mask);
+        assert_eq!(settings.layouts[0].name, "float");
+        assert!(settings.key_map.contains_key(&Key::new(
+            xlib::Mod1Mask | xlib::ShiftMask,
+            XK_Return as u64
+        )));
+        assert!(settings
+            .key_map
+            .contains_key(&Key::new(xlib::Mod1Mask | xlib::ControlMask, XK_F5 as u64)));
+        // Default keybindings use the configured modkey.
+        assert!(settings
+            .key_map
+            .contains_key(&Key::new(xlib::Mod1Mask, XK_j as u64)));
+    }
+
+    #[test]
+    fn report_all_problems() {
+        let xlib = xlib::Xlib::open().unwrap();
+        let contents = r#"gap = -1
+modkey = "Hyper9"
+
+[[keybindings]]
+modifiers = ["Mod4"]
+key = "NotAKey"
+signal = "Quit"
+
+[[keybindings]]
+modifiers = ["Mod4"]
+key = "F1"
+signal = "Quit"
+
+[[keybindings]]
+modifiers = ["Mod4"]
+key = "F1"
+spawn = "xterm"
+"#;
+        match Settings::parse(&xlib, contents) {
+            Err(CritError::Config(errors)) => {
+                let lines = errors.iter().map(|e| e.line).collect::<Vec<_>>();
+                assert_eq!(lines, vec![Some(2), Some(6), Some(14), Some(1)]);
+                assert!(errors[2].message.contains("already bound on line 9"));
+            }
+            _ => panic!("Expected configuration errors"),
+        }
+    }
+
+    #[test]
+    fn report_invalid_signals() {
+        let xlib = xlib::Xlib::open().unwrap();
+        let contents = r#"layouts = ["tile"]
+
+[[keybindings]]
+key = "Mod4+F1"
+signal = { SetLayoutByName = "nope" }
+
+[[keybindings]]
+key = "Mod4+F2"
+signal = { SetLayout = 99 }
+
+[[keybindings]]
+key = "Mod4+F3"
+signal = { ChangeWorkspace = 20 }
+
+[[keybindings]]
+key = "Mod4+F4"
+signal = { SetLayoutByName = "tile" }
+"#;
+        match Settings::parse(&xlib, contents) {
+            Err(CritError::Config(errors)) => {
+                let lines = errors.iter().map(|e| e.line).collect::<Vec<_>>();
+                assert_eq!(lines, vec![Some(5), Some(9), Some(13)]);
+                assert!(errors[0].message.contains("Unknown layout: nope"));
+            }
+            _ => panic!("Expected configuration errors"),
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let settings = parse(
+            r#"
+            workspaces = ["term", "www", "chat", "4", "5", "6", "7", "8", "9"]
+
+            [[rules]]
+            class = "Firefox"
+            workspace = "www"
+
+            [[rules]]
+            class = { regex = "^(Slack|discord)$" }
+            workspace = 3
+            monitor = 1
+            focus = false
+
+            [[rules]]
+            role = "pop-up"
+            geometry = { x = 10, y = 20, width = 640, height = 480 }
+            "#,
+        )
+        .unwrap();
+        let properties = |class: &str, role: Option<&str>| WindowProperties {
+            class: Some(class.to_owned()),
+            role: role.map(str::to_owned),
+            ..Default::default()
+        };
+        let actions =
+            |class, role| RuleActions::from_rules(&settings.rules, &properties(class, role));
+        assert_eq!(actions("Firefox", None).workspace, Some(1));
+        assert_eq!(
+            actions("discord", None),
+            RuleActions {
+                workspace: Some(2),
+                monitor: Some(1),
+                focus: Some(false),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            actions("Firefox", Some("pop-up")).geometry,
+            Some(RuleGeometry {
+                x: 10,
+                y: 20,
+                width: 640,
+                height: 480
+            })
+        );
+        assert_eq!(actions("xterm", None), RuleActions::default());
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert!(parse("gap = -1").is_none());
+        assert!(parse("border_normal_color = \"red\"").is_none());
+        assert!(parse("modkey = \"Hyper9\"").is_none());
+        assert!(parse("layouts = [\"spiral\"]").is_none());
+        assert!(parse("workspaces = [\"one\"]").is_none());
+        assert!(parse("unknown = 1").is_none());
+        assert!(parse("[[keybindings]]\nkey = \"NotAKey\"\nsignal = \"Quit\"").is_none());
+        assert!(parse("[[keybindings]]\nkey = \"q\"").is_none());
+        assert!(parse("[[rules]]\nclass = { regex = \"(\" }").is_none());
+        assert!(parse("[[rules]]\nclass = \"xterm\"\nworkspace = 10").is_none());
+        assert!(parse("[[rules]]\nclass = \"xterm\"\nworkspace = \"www\"").is_none());
+        assert!(parse("[[rules]]\ngeometry = { x = 0, y = 0, width = 0, height = 10 }").is_none());
+        assert!(parse("[[rules]]\nclass = \"xterm\"\nsticky = true").is_none());
+    }
+}
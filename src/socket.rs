@@ -1,17 +1,47 @@
 use crate::{
-    backend::{api::Api, Backend},
-    error::CritResult,
+    backend::{
+        api::Api,
+        signal::{self, Signal},
+        Backend,
+    },
+    error::{CritError, CritResult},
 };
+use serde::{Deserialize, Serialize};
 use std::{
+    env,
     io::Write,
-    os::unix::net::{UnixListener, UnixStream},
-    path::PathBuf,
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
     sync::Arc,
 };
-use tokio::{fs, sync::Mutex, task};
+use tokio::{
+    fs,
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net,
+    sync::{oneshot, Mutex},
+    task,
+};
 
 pub const SOCKET_PATH: &str = "/tmp/critwm_state.sock";
 
+// Command socket of the critwm running on the display in $DISPLAY, so that instances on different
+// displays, such as one nested in Xephyr, do not take each other's socket. It is placed in
+// $XDG_RUNTIME_DIR, or in /tmp if it is not set.
+pub fn command_socket_path() -> PathBuf {
+    command_socket_path_in(
+        env::var_os("XDG_RUNTIME_DIR").map_or_else(|| PathBuf::from("/tmp"), PathBuf::from),
+        env::var("DISPLAY").ok().as_deref(),
+    )
+}
+
+fn command_socket_path_in(dir: PathBuf, display: Option<&str>) -> PathBuf {
+    match display {
+        // Displays may be given as paths, such as those of XQuartz.
+        Some(display) => dir.join(format!("critwm-{}.sock", display.replace('/', "_"))),
+        None => dir.join("critwm.sock"),
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct State {
     streams: Vec<Option<UnixStream>>,
@@ -42,11 +72,16 @@ impl StateSocket {
             fs::remove_file(&self.socket_path).await.ok();
         }
         let state = self.state.clone();
-        let listener = UnixListener::bind(&self.socket_path)?;
+        let listener = net::UnixListener::bind(&self.socket_path)?;
         self.listener = Some(tokio::spawn(async move {
             loop {
-                match listener.accept() {
-                    Ok((mut stream, _)) => {
+                // Accept asynchronously so that the listener does not occupy a runtime thread.
+                match listener.accept().await.and_then(|(stream, _)| {
+                    let stream = stream.into_std()?;
+                    stream.set_nonblocking(false)?;
+                    Ok(stream)
+                }) {
+                    Ok(mut stream) => {
                         let mut state = state.lock().await;
                         if stream.write_all(state.last_state.as_bytes()).is_ok() {
                             info!("Pushed stream: {:?}", stream);
@@ -92,3 +127,143 @@ impl StateSocket {
         Ok(())
     }
 }
+
+// Reply written back to the command socket for every received line.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Reply {
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Reply {
+    pub fn success() -> Self {
+        Self {
+            success: true,
+            error: None,
+        }
+    }
+
+    pub fn error(message: String) -> Self {
+        Self {
+            success: false,
+            error: Some(message),
+        }
+    }
+}
+
+// CommandSocket accepts newline separated JSON encoded signals and pushes them to the signal
+// queue. Each signal is answered with a JSON encoded Reply once it has been handled.
+#[derive(Debug)]
+pub struct CommandSocket {
+    listener: Option<task::JoinHandle<()>>,
+    socket_path: PathBuf,
+}
+
+impl CommandSocket {
+    pub fn new(socket_path: PathBuf) -> Self {
+        Self {
+            listener: None,
+            socket_path,
+        }
+    }
+
+    pub async fn listen(&mut self) -> CritResult<()> {
+        if self.socket_path.exists() {
+            if Self::is_listening(&self.socket_path) {
+                return Err(CritError::Other(format!(
+                    "Another instance is listening on {}",
+                    self.socket_path.display()
+                )));
+            }
+            // Remove stale socket left behind by a previous instance.
+            fs::remove_file(&self.socket_path).await.ok();
+        }
+        let listener = net::UnixListener::bind(&self.socket_path)?;
+        self.listener = Some(tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(Self::handle_stream(stream));
+                    }
+                    Err(e) => {
+                        error!("Command listener accept failed: {:?}", e);
+                    }
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    fn is_listening(socket_path: &Path) -> bool {
+        UnixStream::connect(socket_path).is_ok()
+    }
+
+    async fn handle_stream(stream: net::UnixStream) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply = match serde_json::from_str::<Signal>(&line) {
+                Ok(signal) => {
+                    info!("Received command: {:?}", signal);
+                    Self::send_signal(signal).await
+                }
+                Err(e) => Reply::error(format!("Invalid signal: {}", e)),
+            };
+            // Serializing a Reply cannot fail.
+            let mut json = serde_json::to_string(&reply).unwrap_or_default();
+            json.push('\n');
+            if writer.write_all(json.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    // Queue the signal and wait until the backend has handled it.
+    async fn send_signal(signal: Signal) -> Reply {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = signal::send(signal, Some(sender)) {
+            return Reply::error(e.to_string());
+        }
+        match receiver.await {
+            Ok(Ok(())) => Reply::success(),
+            Ok(Err(e)) => Reply::error(e),
+            Err(_) => Reply::error("Signal was dropped before it was handled".to_owned()),
+        }
+    }
+
+    pub async fn close(&mut self) -> CritResult<()> {
+        if let Some(listener) = self.listener.take() {
+            listener.abort();
+            fs::remove_file(&self.socket_path).await.ok();
+        }
+        info!("Closed command socket");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::command_socket_path_in;
+    use std::path::PathBuf;
+
+    #[test]
+    fn command_socket_per_display() {
+        let dir = PathBuf::from("/run/user/1000");
+        assert_eq!(
+            command_socket_path_in(dir.clone(), Some(":1")),
+            dir.join("critwm-:1.sock")
+        );
+        assert_eq!(
+            command_socket_path_in(dir.clone(), Some("/tmp/launch/org.xquartz:0")),
+            dir.join("critwm-_tmp_launch_org.xquartz:0.sock")
+        );
+        assert_eq!(
+            command_socket_path_in(dir.clone(), None),
+            dir.join("critwm.sock")
+        );
+    }
+}
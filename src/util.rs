@@ -1,8 +1,17 @@
-use crate::backend::signal::{Signal, SIGNAL_STACK};
+use crate::{
+    backend::signal::{self, Signal},
+    error::{CritError, CritResult},
+};
 use std::{
-    os::raw::{c_int, c_uint, c_ulong},
+    ffi::{CStr, CString},
+    os::{
+        raw::{c_int, c_uint, c_ulong},
+        unix::process::CommandExt,
+    },
     process::Command,
+    ptr,
 };
+use x11_dl::xlib;
 
 pub type ModMask = c_uint;
 pub type XKeysym = c_ulong;
@@ -42,6 +51,96 @@ impl Key {
 
 pub type Action = Box<dyn Fn()>;
 
+// Get the modifier mask for a modifier name such as "Shift" or "Mod4". Names are case insensitive.
+pub fn modifier_from_name(name: &str) -> Option<ModMask> {
+    Some(match name.to_lowercase().as_str() {
+        "shift" => xlib::ShiftMask,
+        "lock" => xlib::LockMask,
+        "control" | "ctrl" => xlib::ControlMask,
+        "mod1" | "alt" => xlib::Mod1Mask,
+        "mod2" => xlib::Mod2Mask,
+        "mod3" => xlib::Mod3Mask,
+        "mod4" | "super" => xlib::Mod4Mask,
+        "mod5" => xlib::Mod5Mask,
+        _ => return None,
+    })
+}
+
+// Remove the given lock modifiers from a key or button state, along with any bits that are not
+// modifiers, such as pointer buttons.
+pub fn clean_mask(state: ModMask, locks: ModMask) -> ModMask {
+    let modifiers = xlib::ShiftMask
+        | xlib::LockMask
+        | xlib::ControlMask
+        | xlib::Mod1Mask
+        | xlib::Mod2Mask
+        | xlib::Mod3Mask
+        | xlib::Mod4Mask
+        | xlib::Mod5Mask;
+    state & !locks & modifiers
+}
+
+// Get the name of a key in the form "Mod4+Shift+q".
+pub fn key_name(xlib: &xlib::Xlib, key: &Key) -> String {
+    let modifiers = [
+        (xlib::ShiftMask, "Shift"),
+        (xlib::LockMask, "Lock"),
+        (xlib::ControlMask, "Control"),
+        (xlib::Mod1Mask, "Mod1"),
+        (xlib::Mod2Mask, "Mod2"),
+        (xlib::Mod3Mask, "Mod3"),
+        (xlib::Mod4Mask, "Mod4"),
+        (xlib::Mod5Mask, "Mod5"),
+    ];
+    let mut parts: Vec<String> = modifiers
+        .iter()
+        .filter(|(mask, _)| key.modifier & mask != 0)
+        .map(|(_, name)| name.to_string())
+        .collect();
+    let name = unsafe { (xlib.XKeysymToString)(key.sym) };
+    parts.push(if name.is_null() {
+        format!("{:#x}", key.sym)
+    } else {
+        unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned()
+    });
+    parts.join("+")
+}
+
+// Get the keysym for a key name such as "Return" or "q".
+pub fn keysym_from_name(xlib: &xlib::Xlib, name: &str) -> Option<XKeysym> {
+    let name = CString::new(name).ok()?;
+    match unsafe { (xlib.XStringToKeysym)(name.as_ptr()) } {
+        0 => None,
+        keysym => {
+            // Key events are looked up with the lowercase keysym, so "Q" should match "q".
+            let (mut lower, mut upper) = (0, 0);
+            unsafe { (xlib.XConvertCase)(keysym, &mut lower, &mut upper) };
+            Some(lower)
+        }
+    }
+}
+
+// Parse a key in the form "Mod4+Shift+Return". The last part is the key name and every other part
+// is a modifier name.
+pub fn parse_key(xlib: &xlib::Xlib, value: &str) -> CritResult<Key> {
+    let mut parts: Vec<&str> = value.split('+').map(str::trim).collect();
+    let name = parts.pop().unwrap_or_default();
+    if name.is_empty() {
+        return Err(CritError::Other(format!("Missing key name in {:?}", value)));
+    }
+    let mut modifier = 0;
+    for part in parts {
+        modifier |= modifier_from_name(part).ok_or_else(|| {
+            CritError::Other(format!("Unknown modifier {:?} in {:?}", part, value))
+        })?;
+    }
+    let sym = keysym_from_name(xlib, name)
+        .ok_or_else(|| CritError::Other(format!("Unknown key {:?} in {:?}", name, value)))?;
+    Ok(Key::new(modifier, sym))
+}
+
 macro_rules! key {
     ($modifier:expr, $sym:expr, $action:expr) => {
         (
@@ -51,15 +150,84 @@ macro_rules! key {
     };
 }
 
+// Let the kernel reap spawned programs once they exit, so that they do not linger as zombies.
+// Children left by a previous instance replaced by a restart are reaped here.
+pub fn reap_children() {
+    unsafe {
+        libc::signal(libc::SIGCHLD, libc::SIG_IGN);
+        while libc::waitpid(-1, ptr::null_mut(), libc::WNOHANG) > 0 {}
+    }
+}
+
 pub fn spawn(parts: &str) {
-    let parts = String::from(parts);
     let mut parts = parts.split_whitespace();
-    Command::new(parts.next().unwrap())
-        .args(parts)
-        .spawn()
-        .unwrap();
+    if let Some(program) = parts.next() {
+        let mut command = Command::new(program);
+        command.args(parts);
+        // Ignoring SIGCHLD would be inherited by the program, which may wait for its own children.
+        unsafe {
+            command.pre_exec(|| {
+                libc::signal(libc::SIGCHLD, libc::SIG_DFL);
+                Ok(())
+            });
+        }
+        if let Err(e) = command.spawn() {
+            error!("Failed to spawn {}: {:?}", program, e);
+        }
+    }
 }
 
 pub fn signal(signal: Signal) {
-    SIGNAL_STACK.lock().unwrap().push(signal);
+    if let Err(e) = signal::send(signal, None) {
+        error!("Failed to send signal: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clean_mask, parse_key, Key};
+    use x11_dl::{keysym::*, xlib};
+
+    #[test]
+    fn parse_keys() {
+        let xlib = xlib::Xlib::open().unwrap();
+        assert_eq!(
+            parse_key(&xlib, "Super+Shift+Return").unwrap(),
+            Key::new(xlib::Mod4Mask | xlib::ShiftMask, XK_Return as u64)
+        );
+        assert_eq!(
+            parse_key(&xlib, "Mod1+ctrl+F5").unwrap(),
+            Key::new(xlib::Mod1Mask | xlib::ControlMask, XK_F5 as u64)
+        );
+        assert_eq!(
+            parse_key(&xlib, "Mod4+Q").unwrap(),
+            Key::new(xlib::Mod4Mask, XK_q as u64)
+        );
+        assert_eq!(
+            parse_key(&xlib, "space").unwrap(),
+            Key::new(0, XK_space as u64)
+        );
+    }
+
+    #[test]
+    fn clean_lock_modifiers() {
+        let locks = xlib::LockMask | xlib::Mod2Mask;
+        assert_eq!(
+            clean_mask(xlib::Mod4Mask | xlib::Mod2Mask | xlib::LockMask, locks),
+            xlib::Mod4Mask
+        );
+        assert_eq!(
+            clean_mask(xlib::ShiftMask | xlib::Button1Mask, locks),
+            xlib::ShiftMask
+        );
+    }
+
+    #[test]
+    fn parse_invalid_keys() {
+        let xlib = xlib::Xlib::open().unwrap();
+        assert!(parse_key(&xlib, "").is_err());
+        assert!(parse_key(&xlib, "Mod4+").is_err());
+        assert!(parse_key(&xlib, "Hyper+q").is_err());
+        assert!(parse_key(&xlib, "Mod4+NotAKey").is_err());
+    }
 }